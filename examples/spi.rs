@@ -70,6 +70,7 @@ fn main() -> ! {
                     Some(&mut dp.SYSCONFIG),
                     None,
                 )
+                .unwrap()
                 .downgrade(),
             );
         }
@@ -88,6 +89,7 @@ fn main() -> ! {
                     Some(&mut dp.SYSCONFIG),
                     None,
                 )
+                .unwrap()
                 .downgrade(),
             );
         }
@@ -106,6 +108,7 @@ fn main() -> ! {
                     Some(&mut dp.SYSCONFIG),
                     None,
                 )
+                .unwrap()
                 .downgrade(),
             );
         }
@@ -120,7 +123,7 @@ fn main() -> ! {
                     BLOCKMODE,
                     false,
                 );
-                spi.cfg_transfer(&transfer_cfg);
+                spi.cfg_transfer(&transfer_cfg).unwrap();
             }
         }
         SpiBusSelect::SpiBPortB => {
@@ -133,7 +136,7 @@ fn main() -> ! {
                     BLOCKMODE,
                     false,
                 );
-                spi.cfg_transfer(&transfer_cfg);
+                spi.cfg_transfer(&transfer_cfg).unwrap();
             }
         }
     }
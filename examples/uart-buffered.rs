@@ -0,0 +1,77 @@
+//! Interrupt-driven buffered UART example application. Sends a test string over a UART and then
+//! echoes all received bytes using the ring buffers of the buffered UART
+#![no_main]
+#![no_std]
+
+use core::cell::RefCell;
+use cortex_m::interrupt::Mutex;
+use cortex_m_rt::entry;
+use panic_rtt_target as _;
+use rtt_target::{rprintln, rtt_init_print};
+use va108xx_hal::{
+    gpio::{AltFunc1, Pin, PinsB, PB20, PB21},
+    pac::{self, interrupt, UARTB},
+    prelude::*,
+    uart::{self, BufferedUart},
+};
+
+type UartPins = (Pin<PB21, AltFunc1>, Pin<PB20, AltFunc1>);
+type BufferedUartB = BufferedUart<uart::Uart<UARTB, UartPins>>;
+
+static UART: Mutex<RefCell<Option<BufferedUartB>>> = Mutex::new(RefCell::new(None));
+
+#[entry]
+fn main() -> ! {
+    rtt_init_print!();
+    rprintln!("-- VA108xx buffered UART example application--");
+
+    let mut dp = pac::Peripherals::take().unwrap();
+
+    let gpiob = PinsB::new(&mut dp.SYSCONFIG, Some(dp.IOCONFIG), dp.PORTB);
+    let tx = gpiob.pb21.into_funsel_1();
+    let rx = gpiob.pb20.into_funsel_1();
+
+    let mut uartb = uart::Uart::uartb(
+        dp.UARTB,
+        (tx, rx),
+        115200.bps(),
+        &mut dp.SYSCONFIG,
+        50.mhz().into(),
+    )
+    .unwrap();
+    uartb.route_irq(&mut dp.SYSCONFIG, &mut dp.IRQSEL, interrupt::OC3);
+    let rx_buf = cortex_m::singleton!(: [u8; 256] = [0; 256]).unwrap();
+    let tx_buf = cortex_m::singleton!(: [u8; 256] = [0; 256]).unwrap();
+    let mut buffered = BufferedUart::new(uartb, rx_buf, tx_buf);
+    buffered.write(b"Hello World\r\n");
+    cortex_m::interrupt::free(|cs| UART.borrow(cs).replace(Some(buffered)));
+    unsafe {
+        cortex_m::peripheral::NVIC::unmask(pac::Interrupt::OC3);
+    }
+
+    let mut echo_buf: [u8; 32] = [0; 32];
+    loop {
+        cortex_m::interrupt::free(|cs| {
+            let mut uart = UART.borrow(cs).borrow_mut();
+            let uart = uart.as_mut().unwrap();
+            match uart.read(&mut echo_buf) {
+                Ok(read) => {
+                    uart.write(&echo_buf[0..read]);
+                }
+                Err(uart_error) => {
+                    rprintln!("UART receive error {:?}", uart_error);
+                }
+            }
+        });
+        cortex_m::asm::delay(10000);
+    }
+}
+
+#[interrupt]
+fn OC3() {
+    cortex_m::interrupt::free(|cs| {
+        if let Some(uart) = UART.borrow(cs).borrow_mut().as_mut() {
+            uart.on_interrupt();
+        }
+    });
+}
@@ -26,7 +26,8 @@ fn main() -> ! {
         115200.bps(),
         &mut dp.SYSCONFIG,
         50.mhz().into(),
-    );
+    )
+    .unwrap();
     let (mut tx, mut rx) = uartb.split();
     writeln!(tx, "Hello World\r").unwrap();
     loop {
@@ -0,0 +1,513 @@
+//! # Boot memory support
+//!
+//! The VA108xx copies the application from an external SPI memory into the code RAM after
+//! reset. This memory is connected to SPIC with dedicated ROM pins and chip select 0. The
+//! [`BootMemory`] driver can be used to read and reprogram this memory, for example to perform
+//! an in-field firmware update.
+//!
+//! The driver uses the common command set of SPI FRAM, EEPROM and NOR flash parts. The
+//! differences between these memory types are described with a [`MemoryConfig`]:
+//!
+//! - FRAM can be written without pages and does not need to be erased
+//! - EEPROM is written in pages and does not need to be erased
+//! - NOR flash is written in pages and needs to be erased in sectors before writing
+//!
+//! [`write_image`](BootMemory::write_image) writes a new application image and verifies the
+//! written data with a CRC-32 checksum.
+use embedded_hal::spi::MODE_0;
+use embedded_hal_1::spi::{Operation, SpiDevice as _};
+
+use crate::{
+    pac::SPIC,
+    spi::{ClockError, RomCs, SpiBase, SpiDevice, TransferConfig},
+    time::Hertz,
+};
+
+/// Write status register
+pub const CMD_WRITE_STATUS: u8 = 0x01;
+/// Program up to one page
+pub const CMD_PAGE_PROGRAM: u8 = 0x02;
+pub const CMD_READ: u8 = 0x03;
+pub const CMD_WRITE_DISABLE: u8 = 0x04;
+pub const CMD_READ_STATUS: u8 = 0x05;
+pub const CMD_WRITE_ENABLE: u8 = 0x06;
+/// Erase one sector. This is only supported by flash memories
+pub const CMD_SECTOR_ERASE: u8 = 0x20;
+
+/// A write or erase operation is in progress
+pub const STATUS_BUSY: u8 = 1 << 0;
+/// The write enable latch is set
+pub const STATUS_WRITE_ENABLED: u8 = 1 << 1;
+
+/// Size of the buffer used to read back the memory for the verification
+const VERIFY_CHUNK_SIZE: usize = 64;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum BootMemoryError {
+    Clock(ClockError),
+    /// The page size of the [`MemoryConfig`] is zero or the number of address bytes is not 2
+    /// or 3
+    InvalidConfig,
+    /// The accessed range exceeds the capacity of the memory
+    OutOfRange,
+    /// A page program operation crosses a page boundary
+    PageBoundary,
+    /// The memory was still busy after the configured number of status polls
+    Timeout,
+    /// The write enable latch could not be set. The memory might be write protected
+    WriteProtected,
+    /// The checksum of the image does not match the expected checksum
+    ImageCrcMismatch {
+        expected: u32,
+        actual: u32,
+    },
+    /// The checksum of the written data does not match the checksum of the image
+    VerifyFailed {
+        expected: u32,
+        actual: u32,
+    },
+}
+
+impl From<ClockError> for BootMemoryError {
+    fn from(error: ClockError) -> Self {
+        BootMemoryError::Clock(error)
+    }
+}
+
+/// Properties of the boot memory
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct MemoryConfig {
+    /// Capacity in bytes
+    pub capacity: u32,
+    /// Number of address bytes sent after a command, either 2 or 3
+    pub address_bytes: u8,
+    /// Page size in bytes. A page program operation can not cross a page boundary
+    pub page_size: u32,
+    /// Sector size in bytes for memories which need to be erased before writing
+    pub sector_size: Option<u32>,
+    /// Maximum number of status register reads while waiting for the completion of a write
+    /// or erase operation
+    pub max_busy_polls: u32,
+}
+
+impl MemoryConfig {
+    /// FRAM, which can be written without page boundaries and does not need to be erased.
+    /// Memories with more than 64 kB use 3 address bytes, smaller memories use 2 address bytes
+    pub fn fram(capacity: u32) -> Self {
+        MemoryConfig {
+            capacity,
+            address_bytes: address_bytes_for_capacity(capacity),
+            page_size: capacity,
+            sector_size: None,
+            max_busy_polls: 1000,
+        }
+    }
+
+    /// EEPROM, which is written in pages and does not need to be erased. Memories with more
+    /// than 64 kB use 3 address bytes, smaller memories use 2 address bytes
+    pub fn eeprom(capacity: u32, page_size: u32) -> Self {
+        MemoryConfig {
+            capacity,
+            address_bytes: address_bytes_for_capacity(capacity),
+            page_size,
+            sector_size: None,
+            max_busy_polls: 100_000,
+        }
+    }
+
+    /// NOR flash with 256 byte pages and 4 kB sectors. SPI NOR flash always uses 3 address
+    /// bytes, independently of the capacity
+    pub fn nor_flash(capacity: u32) -> Self {
+        MemoryConfig {
+            capacity,
+            address_bytes: 3,
+            page_size: 256,
+            sector_size: Some(4096),
+            max_busy_polls: 1_000_000,
+        }
+    }
+
+    pub fn max_busy_polls(mut self, polls: u32) -> Self {
+        self.max_busy_polls = polls;
+        self
+    }
+
+    pub fn address_bytes(mut self, address_bytes: u8) -> Self {
+        self.address_bytes = address_bytes;
+        self
+    }
+
+    fn validate(&self) -> Result<(), BootMemoryError> {
+        if self.page_size == 0 || !(2..=3).contains(&self.address_bytes) {
+            return Err(BootMemoryError::InvalidConfig);
+        }
+        Ok(())
+    }
+
+    fn check_range(&self, address: u32, len: usize) -> Result<(), BootMemoryError> {
+        match (address as u64).checked_add(len as u64) {
+            Some(end) if end <= self.capacity as u64 => Ok(()),
+            _ => Err(BootMemoryError::OutOfRange),
+        }
+    }
+
+    /// Command byte followed by the address, most significant byte first
+    fn header(&self, cmd: u8, address: u32) -> ([u8; 4], usize) {
+        let addr = address.to_be_bytes();
+        match self.address_bytes {
+            2 => ([cmd, addr[2], addr[3], 0], 3),
+            _ => ([cmd, addr[1], addr[2], addr[3]], 4),
+        }
+    }
+}
+
+fn address_bytes_for_capacity(capacity: u32) -> u8 {
+    if capacity > 0x1_0000 {
+        3
+    } else {
+        2
+    }
+}
+
+/// Driver for the boot memory connected to SPIC
+pub struct BootMemory {
+    dev: SpiDevice<SPIC, RomCs, u8>,
+    cfg: MemoryConfig,
+}
+
+impl BootMemory {
+    /// Create a new driver. The SPI peripheral can be created with [`SpiBase::spic_rom`].
+    /// The memory is accessed in SPI mode 0 with the given SPI clock.
+    ///
+    /// Returns [`BootMemoryError::InvalidConfig`] if the page size of the memory configuration
+    /// is zero or the number of address bytes is not 2 or 3
+    pub fn new(
+        spi: SpiBase<SPIC, u8>,
+        spi_clk: impl Into<Hertz>,
+        cfg: MemoryConfig,
+    ) -> Result<Self, BootMemoryError> {
+        cfg.validate()?;
+        let transfer_cfg = TransferConfig::new(spi_clk.into(), MODE_0, Some(RomCs), false, false);
+        Ok(BootMemory {
+            dev: SpiDevice::new(spi, transfer_cfg),
+            cfg,
+        })
+    }
+
+    #[inline]
+    pub fn cfg(&self) -> &MemoryConfig {
+        &self.cfg
+    }
+
+    pub fn release(self) -> SpiBase<SPIC, u8> {
+        self.dev.release().0
+    }
+
+    pub fn read_status(&mut self) -> Result<u8, BootMemoryError> {
+        let mut status = [0];
+        self.dev.transaction(&mut [
+            Operation::Write(&[CMD_READ_STATUS]),
+            Operation::Read(&mut status),
+        ])?;
+        Ok(status[0])
+    }
+
+    /// Write the status register. This can be used to clear the block protection bits. The
+    /// write enable latch is set before
+    pub fn write_status(&mut self, status: u8) -> Result<(), BootMemoryError> {
+        self.write_enable()?;
+        self.dev.write(&[CMD_WRITE_STATUS, status])?;
+        self.wait_ready()
+    }
+
+    /// Returns [true] if a write or erase operation is in progress
+    pub fn is_busy(&mut self) -> Result<bool, BootMemoryError> {
+        Ok(self.read_status()? & STATUS_BUSY != 0)
+    }
+
+    /// Poll the status register until no write or erase operation is in progress
+    pub fn wait_ready(&mut self) -> Result<(), BootMemoryError> {
+        for _ in 0..self.cfg.max_busy_polls {
+            if !self.is_busy()? {
+                return Ok(());
+            }
+        }
+        Err(BootMemoryError::Timeout)
+    }
+
+    /// Set the write enable latch, which is required before every write or erase operation.
+    /// Returns [`BootMemoryError::WriteProtected`] if the latch is not set afterwards
+    pub fn write_enable(&mut self) -> Result<(), BootMemoryError> {
+        self.dev.write(&[CMD_WRITE_ENABLE])?;
+        if self.read_status()? & STATUS_WRITE_ENABLED == 0 {
+            return Err(BootMemoryError::WriteProtected);
+        }
+        Ok(())
+    }
+
+    pub fn write_disable(&mut self) -> Result<(), BootMemoryError> {
+        self.dev.write(&[CMD_WRITE_DISABLE])?;
+        Ok(())
+    }
+
+    /// Read data starting at the given address
+    pub fn read(&mut self, address: u32, buf: &mut [u8]) -> Result<(), BootMemoryError> {
+        self.cfg.check_range(address, buf.len())?;
+        let (header, header_len) = self.cfg.header(CMD_READ, address);
+        self.dev.transaction(&mut [
+            Operation::Write(&header[..header_len]),
+            Operation::Read(buf),
+        ])?;
+        Ok(())
+    }
+
+    /// Program data inside a single page and wait for the completion
+    pub fn page_program(&mut self, address: u32, data: &[u8]) -> Result<(), BootMemoryError> {
+        self.cfg.check_range(address, data.len())?;
+        if data.is_empty() {
+            return Ok(());
+        }
+        let page_offset = address % self.cfg.page_size;
+        if page_offset as usize + data.len() > self.cfg.page_size as usize {
+            return Err(BootMemoryError::PageBoundary);
+        }
+        self.write_enable()?;
+        let (header, header_len) = self.cfg.header(CMD_PAGE_PROGRAM, address);
+        self.dev.transaction(&mut [
+            Operation::Write(&header[..header_len]),
+            Operation::Write(data),
+        ])?;
+        self.wait_ready()
+    }
+
+    /// Write data of arbitrary length. The data is split into page program operations. The
+    /// affected sectors of a flash memory need to be erased before
+    pub fn write(&mut self, address: u32, data: &[u8]) -> Result<(), BootMemoryError> {
+        self.cfg.check_range(address, data.len())?;
+        let mut address = address;
+        let mut remaining = data;
+        while !remaining.is_empty() {
+            let page_remaining = (self.cfg.page_size - address % self.cfg.page_size) as usize;
+            let (chunk, rest) = remaining.split_at(core::cmp::min(page_remaining, remaining.len()));
+            self.page_program(address, chunk)?;
+            address += chunk.len() as u32;
+            remaining = rest;
+        }
+        Ok(())
+    }
+
+    /// Erase the sector which contains the given address and wait for the completion. This
+    /// does nothing for memories which do not need to be erased
+    pub fn sector_erase(&mut self, address: u32) -> Result<(), BootMemoryError> {
+        self.cfg.check_range(address, 1)?;
+        if self.cfg.sector_size.is_none() {
+            return Ok(());
+        }
+        self.write_enable()?;
+        let (header, header_len) = self.cfg.header(CMD_SECTOR_ERASE, address);
+        self.dev.write(&header[..header_len])?;
+        self.wait_ready()
+    }
+
+    /// Erase all sectors which overlap with the given range. This does nothing for memories
+    /// which do not need to be erased
+    pub fn erase_range(&mut self, address: u32, len: usize) -> Result<(), BootMemoryError> {
+        self.cfg.check_range(address, len)?;
+        let sector_size = match self.cfg.sector_size {
+            Some(sector_size) if len > 0 => sector_size,
+            _ => return Ok(()),
+        };
+        let end = address + len as u32;
+        let mut sector = address - address % sector_size;
+        while sector < end {
+            self.sector_erase(sector)?;
+            sector += sector_size;
+        }
+        Ok(())
+    }
+
+    /// Calculate the CRC-32 checksum of a memory range
+    pub fn crc32(&mut self, address: u32, len: usize) -> Result<u32, BootMemoryError> {
+        self.cfg.check_range(address, len)?;
+        let mut crc = Crc32::new();
+        let mut buf = [0; VERIFY_CHUNK_SIZE];
+        let mut offset = 0;
+        while offset < len {
+            let chunk_len = core::cmp::min(VERIFY_CHUNK_SIZE, len - offset);
+            self.read(address + offset as u32, &mut buf[..chunk_len])?;
+            crc.update(&buf[..chunk_len]);
+            offset += chunk_len;
+        }
+        Ok(crc.finish())
+    }
+
+    /// Write a new application image. The image is checked against the expected CRC-32
+    /// checksum first, if one is given. The affected sectors are erased, the image is written
+    /// and the written data is verified by reading it back.
+    ///
+    /// Returns the CRC-32 checksum of the image
+    pub fn write_image(
+        &mut self,
+        address: u32,
+        image: &[u8],
+        expected_crc: Option<u32>,
+    ) -> Result<u32, BootMemoryError> {
+        let image_crc = crc32(image);
+        if let Some(expected) = expected_crc {
+            if expected != image_crc {
+                return Err(BootMemoryError::ImageCrcMismatch {
+                    expected,
+                    actual: image_crc,
+                });
+            }
+        }
+        self.cfg.check_range(address, image.len())?;
+        self.erase_range(address, image.len())?;
+        self.write(address, image)?;
+        let written_crc = self.crc32(address, image.len())?;
+        if written_crc != image_crc {
+            return Err(BootMemoryError::VerifyFailed {
+                expected: image_crc,
+                actual: written_crc,
+            });
+        }
+        Ok(image_crc)
+    }
+}
+
+//==================================================================================================
+// CRC
+//==================================================================================================
+
+/// Incremental CRC-32 (IEEE 802.3) calculation.
+///
+/// This type does not access any hardware and can be used and tested on the host as well
+#[derive(Debug, Copy, Clone)]
+pub struct Crc32 {
+    crc: u32,
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Crc32 {
+    pub const fn new() -> Self {
+        Crc32 { crc: 0xffff_ffff }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        for byte in data {
+            self.crc ^= *byte as u32;
+            for _ in 0..8 {
+                let mask = (self.crc & 1).wrapping_neg();
+                self.crc = (self.crc >> 1) ^ (0xedb8_8320 & mask);
+            }
+        }
+    }
+
+    pub fn finish(&self) -> u32 {
+        !self.crc
+    }
+}
+
+/// Calculate the CRC-32 (IEEE 802.3) checksum of the given data
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(data);
+    crc.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_known_vectors() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(
+            crc32(b"The quick brown fox jumps over the lazy dog"),
+            0x414f_a339
+        );
+        assert_eq!(crc32(&[0; 4]), 0x2144_df1c);
+    }
+
+    #[test]
+    fn crc32_empty() {
+        assert_eq!(crc32(&[]), 0);
+        assert_eq!(Crc32::new().finish(), 0);
+    }
+
+    #[test]
+    fn crc32_incremental() {
+        let data = b"123456789";
+        for split in 0..=data.len() {
+            let mut crc = Crc32::default();
+            crc.update(&data[..split]);
+            crc.update(&[]);
+            crc.update(&data[split..]);
+            assert_eq!(crc.finish(), crc32(data));
+        }
+    }
+
+    #[test]
+    fn address_bytes() {
+        assert_eq!(MemoryConfig::fram(0x1_0000).address_bytes, 2);
+        assert_eq!(MemoryConfig::fram(0x2_0000).address_bytes, 3);
+        assert_eq!(MemoryConfig::eeprom(0x8000, 64).address_bytes, 2);
+        assert_eq!(MemoryConfig::eeprom(0x2_0000, 256).address_bytes, 3);
+        // SPI NOR flash always uses 3 address bytes, even for small parts
+        assert_eq!(MemoryConfig::nor_flash(64 * 1024).address_bytes, 3);
+    }
+
+    #[test]
+    fn validate() {
+        assert_eq!(MemoryConfig::fram(0x8000).validate(), Ok(()));
+        assert_eq!(MemoryConfig::nor_flash(0x10_0000).validate(), Ok(()));
+        assert_eq!(
+            MemoryConfig::fram(0).validate(),
+            Err(BootMemoryError::InvalidConfig)
+        );
+        assert_eq!(
+            MemoryConfig::eeprom(0x8000, 0).validate(),
+            Err(BootMemoryError::InvalidConfig)
+        );
+        assert_eq!(
+            MemoryConfig::fram(0x8000).address_bytes(4).validate(),
+            Err(BootMemoryError::InvalidConfig)
+        );
+    }
+
+    #[test]
+    fn check_range() {
+        let cfg = MemoryConfig::fram(0x8000);
+        assert_eq!(cfg.check_range(0, 0x8000), Ok(()));
+        assert_eq!(cfg.check_range(0x7fff, 1), Ok(()));
+        assert_eq!(cfg.check_range(0x8000, 0), Ok(()));
+        assert_eq!(cfg.check_range(0x7fff, 2), Err(BootMemoryError::OutOfRange));
+        assert_eq!(cfg.check_range(0x8000, 1), Err(BootMemoryError::OutOfRange));
+        // The end address must not overflow
+        assert_eq!(
+            cfg.check_range(u32::MAX, usize::MAX),
+            Err(BootMemoryError::OutOfRange)
+        );
+    }
+
+    #[test]
+    fn header() {
+        let cfg = MemoryConfig::fram(0x8000);
+        assert_eq!(cfg.header(CMD_READ, 0x1234), ([CMD_READ, 0x12, 0x34, 0], 3));
+        let cfg = MemoryConfig::nor_flash(64 * 1024);
+        assert_eq!(
+            cfg.header(CMD_PAGE_PROGRAM, 0x1234),
+            ([CMD_PAGE_PROGRAM, 0x00, 0x12, 0x34], 4)
+        );
+        let cfg = MemoryConfig::eeprom(0x2_0000, 256);
+        assert_eq!(
+            cfg.header(CMD_SECTOR_ERASE, 0x01_2345),
+            ([CMD_SECTOR_ERASE, 0x01, 0x23, 0x45], 4)
+        );
+    }
+}
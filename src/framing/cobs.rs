@@ -0,0 +1,382 @@
+//! # COBS framing
+//!
+//! Consistent Overhead Byte Stuffing removes all zero bytes from the frame data with a
+//! maximum overhead of one byte per 254 data bytes. The encoded frames are terminated with a
+//! zero byte, which is used as the frame delimiter. Empty frames are ignored by the decoder.
+use embedded_hal::serial;
+
+use super::{CodecError, FrameError};
+
+/// Frame delimiter
+pub const DELIMITER: u8 = 0x00;
+
+/// Maximum number of data bytes in one COBS block
+const MAX_BLOCK_LEN: usize = 254;
+
+/// Maximum length of the encoded frame including the trailing delimiter
+pub const fn max_encoded_len(data_len: usize) -> usize {
+    data_len + data_len / MAX_BLOCK_LEN + 2
+}
+
+/// Encode a frame into the output buffer and return the length of the encoded frame, including
+/// the trailing delimiter
+pub fn encode(data: &[u8], out: &mut [u8]) -> Result<usize, CodecError> {
+    if out.len() < max_encoded_len(data.len()) {
+        return Err(CodecError::BufferTooSmall);
+    }
+    let mut code_idx = 0;
+    let mut write_idx = 1;
+    let mut code: u8 = 1;
+    for byte in data {
+        if *byte != 0 {
+            out[write_idx] = *byte;
+            write_idx += 1;
+            code += 1;
+        }
+        if *byte == 0 || code == 0xff {
+            out[code_idx] = code;
+            code_idx = write_idx;
+            write_idx += 1;
+            code = 1;
+        }
+    }
+    out[code_idx] = code;
+    out[write_idx] = DELIMITER;
+    Ok(write_idx + 1)
+}
+
+/// Decode a frame without the trailing delimiter in place and return the length of the
+/// decoded data
+pub fn decode_in_place(buf: &mut [u8]) -> Result<usize, CodecError> {
+    let mut read_idx = 0;
+    let mut write_idx = 0;
+    while read_idx < buf.len() {
+        let code = buf[read_idx] as usize;
+        if code == 0 || read_idx + code > buf.len() {
+            return Err(CodecError::InvalidEncoding);
+        }
+        read_idx += 1;
+        for _ in 1..code {
+            let byte = buf[read_idx];
+            if byte == 0 {
+                return Err(CodecError::InvalidEncoding);
+            }
+            // The write index is always smaller than the read index
+            buf[write_idx] = byte;
+            write_idx += 1;
+            read_idx += 1;
+        }
+        if code != MAX_BLOCK_LEN + 1 && read_idx < buf.len() {
+            buf[write_idx] = 0;
+            write_idx += 1;
+        }
+    }
+    Ok(write_idx)
+}
+
+/// Decode a frame without the trailing delimiter into the output buffer and return the length
+/// of the decoded data. The output buffer needs to be at least as large as the encoded frame
+pub fn decode(data: &[u8], out: &mut [u8]) -> Result<usize, CodecError> {
+    let out = out
+        .get_mut(0..data.len())
+        .ok_or(CodecError::BufferTooSmall)?;
+    out.copy_from_slice(data);
+    decode_in_place(out)
+}
+
+/// Encode a frame and write it to a serial interface. This function blocks until all bytes
+/// were written to the interface
+pub fn write_frame<W: serial::Write<u8>>(tx: &mut W, data: &[u8]) -> Result<(), W::Error> {
+    let mut rest = data;
+    loop {
+        let block_len = core::cmp::min(rest.len(), MAX_BLOCK_LEN);
+        let zero_pos = rest[0..block_len].iter().position(|b| *b == 0);
+        let len = zero_pos.unwrap_or(block_len);
+        nb::block!(tx.write(len as u8 + 1))?;
+        for byte in &rest[0..len] {
+            nb::block!(tx.write(*byte))?;
+        }
+        match zero_pos {
+            Some(pos) => rest = &rest[pos + 1..],
+            None => {
+                rest = &rest[len..];
+                // A full block is followed by another block, even if it is empty
+                if len < MAX_BLOCK_LEN {
+                    break;
+                }
+            }
+        }
+    }
+    nb::block!(tx.write(DELIMITER))
+}
+
+/// Incremental COBS decoder which collects the received frame inside a user-provided buffer
+/// and decodes it in place once the delimiter was received
+pub struct Decoder {
+    buf: &'static mut [u8],
+    len: usize,
+    overflow: bool,
+    completed: bool,
+}
+
+impl Decoder {
+    /// Create a new decoder. The maximum length of an encoded frame without the delimiter is
+    /// the length of the buffer
+    pub fn new(buf: &'static mut [u8]) -> Self {
+        Decoder {
+            buf,
+            len: 0,
+            overflow: false,
+            completed: false,
+        }
+    }
+
+    /// Process the next received byte. Returns [true] if a frame was completed, which can then
+    /// be retrieved with [`frame`](Self::frame) until the next byte is pushed.
+    ///
+    /// If a frame could not be decoded, the error is returned once the delimiter was
+    /// received and the frame is discarded
+    pub fn push(&mut self, byte: u8) -> Result<bool, CodecError> {
+        if self.completed {
+            self.reset();
+        }
+        if byte != DELIMITER {
+            match self.buf.get_mut(self.len) {
+                Some(slot) => {
+                    *slot = byte;
+                    self.len += 1;
+                }
+                None => self.overflow = true,
+            }
+            return Ok(false);
+        }
+        if self.overflow {
+            self.reset();
+            return Err(CodecError::BufferTooSmall);
+        }
+        if self.len == 0 {
+            return Ok(false);
+        }
+        match decode_in_place(&mut self.buf[0..self.len]) {
+            Ok(len) => {
+                self.len = len;
+                self.completed = true;
+                Ok(true)
+            }
+            Err(err) => {
+                self.reset();
+                Err(err)
+            }
+        }
+    }
+
+    /// Read all available bytes from a serial interface until a frame was completed. Returns
+    /// [nb::Error::WouldBlock] if no complete frame was received yet
+    pub fn read_frame<R: serial::Read<u8>>(
+        &mut self,
+        rx: &mut R,
+    ) -> nb::Result<&[u8], FrameError<R::Error>> {
+        loop {
+            let byte = rx.read().map_err(|e| e.map(FrameError::Serial))?;
+            if self.push(byte).map_err(|e| nb::Error::Other(e.into()))? {
+                break;
+            }
+        }
+        Ok(self.frame())
+    }
+
+    /// Last completed frame. Returns an empty slice if no frame was completed
+    pub fn frame(&self) -> &[u8] {
+        match self.completed {
+            true => &self.buf[0..self.len],
+            false => &[],
+        }
+    }
+
+    /// Discard the current frame
+    pub fn reset(&mut self) {
+        self.len = 0;
+        self.overflow = false;
+        self.completed = false;
+    }
+
+    /// Release the frame buffer
+    pub fn release(self) -> &'static mut [u8] {
+        self.buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::{vec, vec::Vec};
+
+    use super::super::mock::{static_buf, SerialRx, SerialTx};
+    use super::*;
+
+    fn encode_vec(data: &[u8]) -> Vec<u8> {
+        let mut out = vec![0; max_encoded_len(data.len())];
+        let len = encode(data, &mut out).unwrap();
+        out.truncate(len);
+        out
+    }
+
+    /// Encode with all encoders and decode with all decoders
+    fn check_round_trip(data: &[u8]) {
+        let encoded = encode_vec(data);
+        assert!(encoded.len() <= max_encoded_len(data.len()));
+        assert_eq!(encoded.last(), Some(&DELIMITER));
+        let frame = &encoded[0..encoded.len() - 1];
+        assert!(!frame.contains(&0));
+
+        let mut tx = SerialTx::default();
+        write_frame(&mut tx, data).unwrap();
+        assert_eq!(tx.0, encoded);
+
+        let mut out = vec![0; frame.len()];
+        let len = decode(frame, &mut out).unwrap();
+        assert_eq!(&out[0..len], data);
+
+        let mut decoder = Decoder::new(static_buf(frame.len()));
+        for byte in frame {
+            assert_eq!(decoder.push(*byte), Ok(false));
+        }
+        assert_eq!(decoder.push(DELIMITER), Ok(true));
+        assert_eq!(decoder.frame(), data);
+    }
+
+    #[test]
+    fn encode_examples() {
+        assert_eq!(encode_vec(&[]), [0x01, 0x00]);
+        assert_eq!(encode_vec(&[0x00]), [0x01, 0x01, 0x00]);
+        assert_eq!(encode_vec(&[0x00, 0x00]), [0x01, 0x01, 0x01, 0x00]);
+        assert_eq!(
+            encode_vec(&[0x11, 0x22, 0x00, 0x33]),
+            [0x03, 0x11, 0x22, 0x02, 0x33, 0x00]
+        );
+        assert_eq!(encode_vec(&[0x11, 0x00]), [0x02, 0x11, 0x01, 0x00]);
+    }
+
+    #[test]
+    fn round_trip_zero_runs() {
+        check_round_trip(&[]);
+        check_round_trip(&[0x00]);
+        check_round_trip(&[0x00; 300]);
+        check_round_trip(&[0x01, 0x00, 0x00, 0x02, 0x00]);
+    }
+
+    #[test]
+    fn round_trip_block_boundary() {
+        for len in [253, 254, 255, 508, 509] {
+            let data: Vec<u8> = (0..len).map(|i| (i % 255) as u8 + 1).collect();
+            check_round_trip(&data);
+            // Zero bytes right before, at and after the block boundary
+            for zero_pos in [0, 252, 253, 254, len - 1]
+                .into_iter()
+                .filter(|pos| *pos < len)
+            {
+                let mut data = data.clone();
+                data[zero_pos] = 0;
+                check_round_trip(&data);
+            }
+        }
+    }
+
+    #[test]
+    fn full_block_is_followed_by_code() {
+        let data = [0x01; 254];
+        let encoded = encode_vec(&data);
+        assert_eq!(encoded[0], 0xff);
+        assert_eq!(encoded[255..], [0x01, 0x00]);
+        // Encoders which omit the empty block after a full block are supported by the decoder
+        let mut canonical = encoded[0..255].to_vec();
+        assert_eq!(decode_in_place(&mut canonical), Ok(254));
+        assert_eq!(canonical[0..254], data);
+    }
+
+    #[test]
+    fn truncated_input() {
+        // The code byte announces more data than available
+        let mut frame = [0x05, 0x11, 0x22];
+        assert_eq!(
+            decode_in_place(&mut frame),
+            Err(CodecError::InvalidEncoding)
+        );
+        let encoded = encode_vec(&[0x01; 254]);
+        let mut frame = encoded[0..200].to_vec();
+        assert_eq!(
+            decode_in_place(&mut frame),
+            Err(CodecError::InvalidEncoding)
+        );
+    }
+
+    #[test]
+    fn corrupt_input() {
+        // Zero bytes and zero codes are not allowed inside a frame
+        let mut frame = [0x03, 0x11, 0x00];
+        assert_eq!(
+            decode_in_place(&mut frame),
+            Err(CodecError::InvalidEncoding)
+        );
+        let mut frame = [0x00, 0x11];
+        assert_eq!(
+            decode_in_place(&mut frame),
+            Err(CodecError::InvalidEncoding)
+        );
+
+        let mut decoder = Decoder::new(static_buf(8));
+        for byte in [0x05, 0x11, 0x22] {
+            assert_eq!(decoder.push(byte), Ok(false));
+        }
+        assert_eq!(decoder.push(DELIMITER), Err(CodecError::InvalidEncoding));
+        assert_eq!(decoder.frame(), &[]);
+        // The decoder recovers with the next frame
+        for byte in [0x02, 0x11] {
+            assert_eq!(decoder.push(byte), Ok(false));
+        }
+        assert_eq!(decoder.push(DELIMITER), Ok(true));
+        assert_eq!(decoder.frame(), &[0x11]);
+    }
+
+    #[test]
+    fn output_buffer_too_small() {
+        let data = [0x11, 0x22, 0x33];
+        let mut out = [0; 5];
+        assert_eq!(
+            encode(&data, &mut out[0..4]),
+            Err(CodecError::BufferTooSmall)
+        );
+        assert_eq!(encode(&data, &mut out), Ok(5));
+        let mut decoded = [0; 3];
+        assert_eq!(
+            decode(&out[0..4], &mut decoded),
+            Err(CodecError::BufferTooSmall)
+        );
+        let mut decoded = [0; 4];
+        assert_eq!(decode(&out[0..4], &mut decoded), Ok(3));
+        assert_eq!(decoded[0..3], data);
+    }
+
+    #[test]
+    fn decoder_buffer_too_small() {
+        let mut decoder = Decoder::new(static_buf(3));
+        for byte in [0x04, 0x11, 0x22, 0x33] {
+            assert_eq!(decoder.push(byte), Ok(false));
+        }
+        assert_eq!(decoder.push(DELIMITER), Err(CodecError::BufferTooSmall));
+        // Empty frames are ignored
+        assert_eq!(decoder.push(DELIMITER), Ok(false));
+    }
+
+    #[test]
+    fn read_frame_from_serial() {
+        let mut rx = SerialRx::default();
+        let mut decoder = Decoder::new(static_buf(8));
+        rx.0.extend(encode_vec(&[0x11, 0x00, 0x22]));
+        rx.0.extend(encode_vec(&[0x33]));
+        assert_eq!(decoder.read_frame(&mut rx), Ok(&[0x11, 0x00, 0x22][..]));
+        assert_eq!(decoder.read_frame(&mut rx), Ok(&[0x33][..]));
+        assert_eq!(decoder.read_frame(&mut rx), Err(nb::Error::WouldBlock));
+    }
+}
@@ -0,0 +1,83 @@
+//! # Framing layers for serial interfaces
+//!
+//! This module provides [SLIP](slip) and [COBS](cobs) encoders and decoders to transfer packets
+//! over a byte stream, for example a UART. It is only available if the `framing` feature is
+//! enabled.
+//!
+//! The codecs do not access any hardware and can be used and tested on the host as well. They
+//! do not allocate and work on user-provided buffers. The decoders process the received data
+//! byte by byte and can be fed directly from a [`serial::Read`] implementation, for example
+//! [`uart::Rx`](crate::uart::Rx). The frame writers encode the data on the fly and write it to
+//! a [`serial::Write`] implementation without requiring an intermediate buffer.
+//!
+//! [`serial::Read`]: embedded_hal::serial::Read
+//! [`serial::Write`]: embedded_hal::serial::Write
+pub mod cobs;
+pub mod slip;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum CodecError {
+    /// The output or frame buffer is too small
+    BufferTooSmall,
+    /// The received data is not encoded properly
+    InvalidEncoding,
+}
+
+/// Error returned when reading frames from a serial interface
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum FrameError<E> {
+    /// Error reported by the serial interface
+    Serial(E),
+    /// The received frame could not be decoded. The frame is discarded
+    Codec(CodecError),
+}
+
+impl<E> From<CodecError> for FrameError<E> {
+    fn from(err: CodecError) -> Self {
+        FrameError::Codec(err)
+    }
+}
+
+/// Serial interface mocks for the codec tests
+#[cfg(test)]
+mod mock {
+    extern crate std;
+
+    use core::convert::Infallible;
+    use std::{boxed::Box, collections::VecDeque, vec, vec::Vec};
+
+    use embedded_hal::serial;
+
+    pub fn static_buf(len: usize) -> &'static mut [u8] {
+        Box::leak(vec![0; len].into_boxed_slice())
+    }
+
+    /// Collects all written bytes
+    #[derive(Default)]
+    pub struct SerialTx(pub Vec<u8>);
+
+    impl serial::Write<u8> for SerialTx {
+        type Error = Infallible;
+
+        fn write(&mut self, word: u8) -> nb::Result<(), Infallible> {
+            self.0.push(word);
+            Ok(())
+        }
+
+        fn flush(&mut self) -> nb::Result<(), Infallible> {
+            Ok(())
+        }
+    }
+
+    /// Returns the queued bytes and [nb::Error::WouldBlock] once all bytes were read
+    #[derive(Default)]
+    pub struct SerialRx(pub VecDeque<u8>);
+
+    impl serial::Read<u8> for SerialRx {
+        type Error = Infallible;
+
+        fn read(&mut self) -> nb::Result<u8, Infallible> {
+            self.0.pop_front().ok_or(nb::Error::WouldBlock)
+        }
+    }
+}
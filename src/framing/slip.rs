@@ -0,0 +1,297 @@
+//! # SLIP framing
+//!
+//! Serial Line Internet Protocol framing as specified in RFC 1055. Every frame is enclosed by
+//! [`END`] bytes. [`END`] and [`ESC`] bytes in the frame data are replaced by two byte escape
+//! sequences. Empty frames are ignored by the decoder, so the leading [`END`] byte can be used
+//! to flush line noise at the receiver.
+use embedded_hal::serial;
+
+use super::{CodecError, FrameError};
+
+pub const END: u8 = 0xc0;
+pub const ESC: u8 = 0xdb;
+pub const ESC_END: u8 = 0xdc;
+pub const ESC_ESC: u8 = 0xdd;
+
+/// Maximum length of the encoded frame including the leading and trailing [`END`] bytes
+pub const fn max_encoded_len(data_len: usize) -> usize {
+    data_len * 2 + 2
+}
+
+/// Encode a frame into the output buffer and return the length of the encoded frame, including
+/// the leading and trailing [`END`] bytes
+pub fn encode(data: &[u8], out: &mut [u8]) -> Result<usize, CodecError> {
+    let mut written = 0;
+    let mut push = |byte: u8| {
+        let slot = out.get_mut(written).ok_or(CodecError::BufferTooSmall)?;
+        *slot = byte;
+        written += 1;
+        Ok(())
+    };
+    push(END)?;
+    for byte in data {
+        match *byte {
+            END => {
+                push(ESC)?;
+                push(ESC_END)?;
+            }
+            ESC => {
+                push(ESC)?;
+                push(ESC_ESC)?;
+            }
+            byte => push(byte)?,
+        }
+    }
+    push(END)?;
+    Ok(written)
+}
+
+/// Encode a frame and write it to a serial interface. This function blocks until all bytes
+/// were written to the interface
+pub fn write_frame<W: serial::Write<u8>>(tx: &mut W, data: &[u8]) -> Result<(), W::Error> {
+    nb::block!(tx.write(END))?;
+    for byte in data {
+        match *byte {
+            END => {
+                nb::block!(tx.write(ESC))?;
+                nb::block!(tx.write(ESC_END))?;
+            }
+            ESC => {
+                nb::block!(tx.write(ESC))?;
+                nb::block!(tx.write(ESC_ESC))?;
+            }
+            byte => nb::block!(tx.write(byte))?,
+        }
+    }
+    nb::block!(tx.write(END))
+}
+
+/// Incremental SLIP decoder which collects the decoded frame inside a user-provided buffer
+pub struct Decoder {
+    buf: &'static mut [u8],
+    len: usize,
+    escape: bool,
+    error: Option<CodecError>,
+    completed: bool,
+}
+
+impl Decoder {
+    /// Create a new decoder. The maximum length of a decoded frame is the length of the buffer
+    pub fn new(buf: &'static mut [u8]) -> Self {
+        Decoder {
+            buf,
+            len: 0,
+            escape: false,
+            error: None,
+            completed: false,
+        }
+    }
+
+    /// Process the next received byte. Returns [true] if a frame was completed, which can then
+    /// be retrieved with [`frame`](Self::frame) until the next byte is pushed.
+    ///
+    /// If a frame could not be decoded, the error is returned once the end of the frame was
+    /// received and the frame is discarded
+    pub fn push(&mut self, byte: u8) -> Result<bool, CodecError> {
+        if self.completed {
+            self.reset();
+        }
+        if byte == END {
+            if let Some(err) = self.error {
+                self.reset();
+                return Err(err);
+            }
+            if self.escape {
+                self.reset();
+                return Err(CodecError::InvalidEncoding);
+            }
+            if self.len == 0 {
+                return Ok(false);
+            }
+            self.completed = true;
+            return Ok(true);
+        }
+        if self.error.is_some() {
+            return Ok(false);
+        }
+        let byte = match (self.escape, byte) {
+            (false, ESC) => {
+                self.escape = true;
+                return Ok(false);
+            }
+            (false, byte) => byte,
+            (true, ESC_END) => END,
+            (true, ESC_ESC) => ESC,
+            (true, _) => {
+                self.error = Some(CodecError::InvalidEncoding);
+                return Ok(false);
+            }
+        };
+        self.escape = false;
+        match self.buf.get_mut(self.len) {
+            Some(slot) => {
+                *slot = byte;
+                self.len += 1;
+            }
+            None => self.error = Some(CodecError::BufferTooSmall),
+        }
+        Ok(false)
+    }
+
+    /// Read all available bytes from a serial interface until a frame was completed. Returns
+    /// [nb::Error::WouldBlock] if no complete frame was received yet
+    pub fn read_frame<R: serial::Read<u8>>(
+        &mut self,
+        rx: &mut R,
+    ) -> nb::Result<&[u8], FrameError<R::Error>> {
+        loop {
+            let byte = rx.read().map_err(|e| e.map(FrameError::Serial))?;
+            if self.push(byte).map_err(|e| nb::Error::Other(e.into()))? {
+                break;
+            }
+        }
+        Ok(self.frame())
+    }
+
+    /// Last completed frame. Returns an empty slice if no frame was completed
+    pub fn frame(&self) -> &[u8] {
+        match self.completed {
+            true => &self.buf[0..self.len],
+            false => &[],
+        }
+    }
+
+    /// Discard the current frame
+    pub fn reset(&mut self) {
+        self.len = 0;
+        self.escape = false;
+        self.error = None;
+        self.completed = false;
+    }
+
+    /// Release the frame buffer
+    pub fn release(self) -> &'static mut [u8] {
+        self.buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::{vec, vec::Vec};
+
+    use super::super::mock::{static_buf, SerialRx, SerialTx};
+    use super::*;
+
+    fn encode_vec(data: &[u8]) -> Vec<u8> {
+        let mut out = vec![0; max_encoded_len(data.len())];
+        let len = encode(data, &mut out).unwrap();
+        out.truncate(len);
+        out
+    }
+
+    /// Push all bytes into the decoder and return the completed frames
+    fn decode_all(decoder: &mut Decoder, encoded: &[u8]) -> Vec<Result<Vec<u8>, CodecError>> {
+        let mut frames = Vec::new();
+        for byte in encoded {
+            match decoder.push(*byte) {
+                Ok(true) => frames.push(Ok(decoder.frame().to_vec())),
+                Ok(false) => (),
+                Err(err) => frames.push(Err(err)),
+            }
+        }
+        frames
+    }
+
+    #[test]
+    fn end_and_esc_are_escaped() {
+        let encoded = encode_vec(&[0x01, END, ESC, 0x02]);
+        assert_eq!(encoded, [END, 0x01, ESC, ESC_END, ESC, ESC_ESC, 0x02, END]);
+        let mut tx = SerialTx::default();
+        write_frame(&mut tx, &[0x01, END, ESC, 0x02]).unwrap();
+        assert_eq!(tx.0, encoded);
+    }
+
+    #[test]
+    fn round_trip() {
+        let data: Vec<u8> = (0..=255).collect();
+        let encoded = encode_vec(&data);
+        let mut decoder = Decoder::new(static_buf(256));
+        assert_eq!(decode_all(&mut decoder, &encoded), [Ok(data)]);
+    }
+
+    #[test]
+    fn worst_case_length() {
+        let data = [END, ESC, END];
+        let encoded = encode_vec(&data);
+        assert_eq!(encoded.len(), max_encoded_len(data.len()));
+        let mut decoder = Decoder::new(static_buf(3));
+        assert_eq!(decode_all(&mut decoder, &encoded), [Ok(data.to_vec())]);
+    }
+
+    #[test]
+    fn empty_frames_are_ignored() {
+        let mut decoder = Decoder::new(static_buf(8));
+        let mut encoded = vec![END, END];
+        encoded.extend(encode_vec(&[0x01]));
+        encoded.extend(encode_vec(&[]));
+        assert_eq!(decode_all(&mut decoder, &encoded), [Ok(vec![0x01])]);
+    }
+
+    #[test]
+    fn encode_output_buffer_too_small() {
+        let mut out = [0; 5];
+        assert_eq!(
+            encode(&[END, END], &mut out),
+            Err(CodecError::BufferTooSmall)
+        );
+        assert_eq!(encode(&[0x01, 0x02, 0x03], &mut out), Ok(5));
+        assert_eq!(encode(&[], &mut out[0..1]), Err(CodecError::BufferTooSmall));
+    }
+
+    #[test]
+    fn decoder_buffer_too_small() {
+        let mut decoder = Decoder::new(static_buf(2));
+        let mut encoded = encode_vec(&[0x01, 0x02, 0x03]);
+        // The decoder recovers with the next frame
+        encoded.extend(encode_vec(&[0x04, END]));
+        assert_eq!(
+            decode_all(&mut decoder, &encoded),
+            [Err(CodecError::BufferTooSmall), Ok(vec![0x04, END])]
+        );
+    }
+
+    #[test]
+    fn invalid_escape_sequence() {
+        let mut decoder = Decoder::new(static_buf(8));
+        let mut encoded = vec![END, 0x01, ESC, 0x02, END];
+        encoded.extend(encode_vec(&[0x03]));
+        assert_eq!(
+            decode_all(&mut decoder, &encoded),
+            [Err(CodecError::InvalidEncoding), Ok(vec![0x03])]
+        );
+    }
+
+    #[test]
+    fn truncated_escape_sequence() {
+        let mut decoder = Decoder::new(static_buf(8));
+        assert_eq!(
+            decode_all(&mut decoder, &[END, 0x01, ESC, END]),
+            [Err(CodecError::InvalidEncoding)]
+        );
+    }
+
+    #[test]
+    fn read_frame_from_serial() {
+        let mut rx = SerialRx::default();
+        let mut decoder = Decoder::new(static_buf(8));
+        let encoded = encode_vec(&[0x01, ESC, 0x02]);
+        // The frame is not complete yet
+        rx.0.extend(&encoded[0..3]);
+        assert_eq!(decoder.read_frame(&mut rx), Err(nb::Error::WouldBlock));
+        assert_eq!(decoder.frame(), &[]);
+        rx.0.extend(&encoded[3..]);
+        assert_eq!(decoder.read_frame(&mut rx), Ok(&[0x01, ESC, 0x02][..]));
+    }
+}
@@ -0,0 +1,253 @@
+//! # Async I2C support
+//!
+//! With the `async` feature, the [`I2cMaster`] implements the [`embedded_hal_async`] [`I2c`]
+//! trait for 7 bit and 10 bit addresses. The task waits for the FIFO trigger level, idle and
+//! error interrupts of the I2C master and is woken from [`on_interrupt_async`], which needs to
+//! be called in the interrupt handler the I2C master interrupt was routed to with
+//! [`route_irq`](I2cMaster::route_irq).
+//!
+//! Consecutive operations of the same kind are merged into a single I2C transfer. A repeated
+//! start is generated between reads and writes and the transaction ends with a stop condition.
+//! Like for the blocking API, a single transfer can contain at most 0x7fe bytes.
+use core::future::poll_fn;
+use core::task::Poll;
+
+use embedded_hal_1::i2c::{ErrorType, Operation};
+use embedded_hal_async::i2c::I2c;
+
+use super::{
+    i2c_base, Error, I2cAddress, I2cBase, I2cCmd, I2cDirection, I2cMaster, Instance,
+    SevenBitAddress, TenBitAddress,
+};
+use crate::waker::IrqWaker;
+
+/// Depth of the TX and RX FIFO
+const FIFO_DEPTH: u32 = 16;
+/// Maximum number of bytes of a single transfer
+const MAX_WORDS: usize = 0x7fe;
+
+static WAKERS: [IrqWaker; 2] = [IrqWaker::new(), IrqWaker::new()];
+
+/// This function should be called in the interrupt handler the I2C master interrupt was routed
+/// to if the async API is used. It masks the I2C master interrupts again and wakes the waiting
+/// task
+pub fn on_interrupt_async<I2C: Instance>() {
+    unsafe { &(*I2C::ptr()) }.irq_enb.reset();
+    WAKERS[I2C::IDX as usize].wake();
+}
+
+impl<I2C, ADDR> ErrorType for I2cMaster<I2C, ADDR> {
+    type Error = Error;
+}
+
+fn write_buf<'a>(op: &'a Operation<'_>) -> &'a [u8] {
+    match op {
+        Operation::Write(buf) => buf,
+        Operation::Read(_) => &[],
+    }
+}
+
+fn read_buf<'a>(op: &'a mut Operation<'_>) -> &'a mut [u8] {
+    match op {
+        Operation::Read(buf) => buf,
+        Operation::Write(_) => &mut [],
+    }
+}
+
+impl<I2C: Instance, ADDR> I2cMaster<I2C, ADDR> {
+    async fn transaction_async(
+        &mut self,
+        addr: I2cAddress,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Error> {
+        let mut start = 0;
+        while start < operations.len() {
+            let is_read = matches!(operations[start], Operation::Read(_));
+            let end = start
+                + operations[start..]
+                    .iter()
+                    .take_while(|op| matches!(op, Operation::Read(_)) == is_read)
+                    .count();
+            let cmd = if end == operations.len() {
+                I2cCmd::StartWithStop
+            } else {
+                I2cCmd::Start
+            };
+            if is_read {
+                self.read_async(addr, cmd, &mut operations[start..end])
+                    .await?;
+            } else {
+                self.write_async(addr, cmd, &operations[start..end]).await?;
+            }
+            start = end;
+        }
+        Ok(())
+    }
+
+    /// Start a transfer. The number of words, the address and the command are written
+    fn start_transfer(&self, addr: I2cAddress, dir: I2cDirection, cmd: I2cCmd, len: usize) {
+        let i2c = &self.i2c_base.i2c;
+        i2c.irq_clr.write(|w| {
+            w.idle().set_bit();
+            w.waiting().set_bit();
+            w.arblost().set_bit();
+            w.nackaddr().set_bit();
+            w.nackdata().set_bit()
+        });
+        i2c.words.write(|w| unsafe { w.bits(len as u32) });
+        let (addr, addr_mode_bit) = I2cBase::<I2C>::unwrap_addr(addr);
+        i2c.address
+            .write(|w| unsafe { w.bits(dir as u32 | (addr << 1) as u32 | addr_mode_bit) });
+        i2c.cmd.write(|w| unsafe { w.bits(cmd as u32) });
+    }
+
+    async fn write_async(
+        &mut self,
+        addr: I2cAddress,
+        cmd: I2cCmd,
+        operations: &[Operation<'_>],
+    ) -> Result<(), Error> {
+        let len: usize = operations.iter().map(|op| write_buf(op).len()).sum();
+        if len > MAX_WORDS {
+            return Err(Error::DataTooLarge);
+        }
+        let i2c = &self.i2c_base.i2c;
+        i2c.fifo_clr.write(|w| w.txfifo().set_bit());
+        i2c.txfifoirqtrg
+            .write(|w| unsafe { w.bits(FIFO_DEPTH / 2) });
+        let mut bytes = operations
+            .iter()
+            .flat_map(|op| write_buf(op).iter().copied())
+            .peekable();
+        load_tx_fifo(i2c, &mut bytes);
+        self.start_transfer(addr, I2cDirection::Send, cmd, len);
+        let result = poll_fn(|cx| {
+            load_tx_fifo(i2c, &mut bytes);
+            let status = i2c.status.read();
+            if status.arblost().bit_is_set() {
+                return Poll::Ready(Err(Error::ArbitrationLost));
+            } else if status.nackaddr().bit_is_set() {
+                return Poll::Ready(Err(Error::NackAddr));
+            } else if status.nackdata().bit_is_set() {
+                return Poll::Ready(Err(Error::NackData));
+            }
+            let all_loaded = bytes.peek().is_none();
+            // The controller waits for the next command if the transfer is not finished with a
+            // stop condition
+            if all_loaded
+                && status.txempty().bit_is_set()
+                && (status.idle().bit_is_set() || status.waiting().bit_is_set())
+            {
+                return Poll::Ready(Ok(()));
+            }
+            WAKERS[I2C::IDX as usize].register(cx.waker());
+            i2c.irq_enb.write(|w| {
+                w.idle().set_bit();
+                w.waiting().set_bit();
+                w.arblost().set_bit();
+                w.nackaddr().set_bit();
+                w.nackdata().set_bit();
+                w.txready().bit(!all_loaded)
+            });
+            Poll::Pending
+        })
+        .await;
+        if result.is_err() {
+            i2c.fifo_clr.write(|w| w.txfifo().set_bit());
+            if cmd == I2cCmd::Start {
+                i2c.cmd.write(|w| unsafe { w.bits(I2cCmd::Stop as u32) });
+            }
+        }
+        result
+    }
+
+    async fn read_async(
+        &mut self,
+        addr: I2cAddress,
+        cmd: I2cCmd,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Error> {
+        let len: usize = operations.iter_mut().map(|op| read_buf(op).len()).sum();
+        if len > MAX_WORDS {
+            return Err(Error::DataTooLarge);
+        }
+        let i2c = &self.i2c_base.i2c;
+        i2c.fifo_clr.write(|w| w.rxfifo().set_bit());
+        i2c.rxfifoirqtrg
+            .write(|w| unsafe { w.bits(FIFO_DEPTH / 2) });
+        self.start_transfer(addr, I2cDirection::Read, cmd, len);
+        let mut slots = operations.iter_mut().flat_map(|op| read_buf(op).iter_mut());
+        let mut read_bytes = 0;
+        let result = poll_fn(|cx| {
+            // The status is read before the RX FIFO is emptied, so all bytes are read if the
+            // controller was idle already
+            let status = i2c.status.read();
+            while i2c.status.read().rxnempty().bit_is_set() {
+                let byte = i2c.data.read().bits() as u8;
+                if let Some(slot) = slots.next() {
+                    *slot = byte;
+                }
+                read_bytes += 1;
+            }
+            if status.arblost().bit_is_set() {
+                return Poll::Ready(Err(Error::ArbitrationLost));
+            } else if status.nackaddr().bit_is_set() {
+                return Poll::Ready(Err(Error::NackAddr));
+            }
+            let finished = status.idle().bit_is_set() || status.waiting().bit_is_set();
+            if finished && read_bytes >= len {
+                return Poll::Ready(Ok(()));
+            } else if status.idle().bit_is_set() {
+                return Poll::Ready(Err(Error::InsufficientDataReceived));
+            }
+            WAKERS[I2C::IDX as usize].register(cx.waker());
+            i2c.irq_enb.write(|w| {
+                w.idle().set_bit();
+                w.waiting().set_bit();
+                w.arblost().set_bit();
+                w.nackaddr().set_bit();
+                w.rxready().set_bit()
+            });
+            Poll::Pending
+        })
+        .await;
+        if result.is_err() {
+            i2c.fifo_clr.write(|w| w.rxfifo().set_bit());
+        }
+        result
+    }
+}
+
+/// Write bytes to the TX FIFO until it is full
+fn load_tx_fifo(
+    i2c: &i2c_base::RegisterBlock,
+    bytes: &mut core::iter::Peekable<impl Iterator<Item = u8>>,
+) {
+    while bytes.peek().is_some() && i2c.status.read().txnfull().bit_is_set() {
+        if let Some(byte) = bytes.next() {
+            i2c.data.write(|w| unsafe { w.bits(byte as u32) });
+        }
+    }
+}
+
+impl<I2C: Instance> I2c<SevenBitAddress> for I2cMaster<I2C, SevenBitAddress> {
+    async fn transaction(
+        &mut self,
+        address: SevenBitAddress,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.transaction_async(I2cAddress::Regular(address), operations)
+            .await
+    }
+}
+
+impl<I2C: Instance> I2c<TenBitAddress> for I2cMaster<I2C, TenBitAddress> {
+    async fn transaction(
+        &mut self,
+        address: TenBitAddress,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.transaction_async(I2cAddress::TenBit(address), operations)
+            .await
+    }
+}
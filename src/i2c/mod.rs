@@ -3,17 +3,29 @@
 //! ## Examples
 //!
 //! - [REB1 I2C temperature sensor example](https://github.com/robamu-org/vorago-reb1-rs/blob/main/examples/temp-sensor.rs)
+//!
+//! ## Features
+//!
+//! - `async`: Implements the [`embedded_hal_async::i2c::I2c`] trait for the [`I2cMaster`]
 use crate::{
     clock::{enable_peripheral_clock, PeripheralClocks},
-    pac::{I2CA, I2CB, SYSCONFIG},
+    pac::{i2ca as i2c_base, Interrupt, I2CA, I2CB, IRQSEL, SYSCONFIG},
     time::Hertz,
     Sealed,
 };
-use core::marker::PhantomData;
+use core::{marker::PhantomData, ops::Deref};
 use embedded_hal::blocking::i2c::{Read, Write, WriteIter, WriteIterRead, WriteRead};
+use embedded_hal_1::i2c::{ErrorKind, NoAcknowledgeSource};
 
 pub use embedded_hal::blocking::i2c::{SevenBitAddress, TenBitAddress};
 
+#[cfg(feature = "async")]
+mod asynch;
+#[cfg(feature = "async")]
+pub use asynch::*;
+
+const IRQ_DST_NONE: u32 = 0xffffffff;
+
 //==================================================================================================
 // Defintions
 //==================================================================================================
@@ -38,6 +50,20 @@ pub enum Error {
     WrongAddrMode,
 }
 
+impl embedded_hal_1::i2c::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Error::ArbitrationLost => ErrorKind::ArbitrationLoss,
+            Error::NackAddr => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address),
+            Error::NackData => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data),
+            Error::InvalidTimingParams
+            | Error::InsufficientDataReceived
+            | Error::DataTooLarge
+            | Error::WrongAddrMode => ErrorKind::Other,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Copy, Clone)]
 enum I2cCmd {
     Start = 0b00,
@@ -210,6 +236,32 @@ impl Sealed for SlaveConfig {}
 // I2C Base
 //==================================================================================================
 
+pub trait Instance: Deref<Target = i2c_base::RegisterBlock> {
+    /// Index of the I2C peripheral, used for the IRQSEL interrupt redirection
+    const IDX: u8;
+
+    fn ptr() -> *const i2c_base::RegisterBlock;
+}
+
+macro_rules! i2c_instance {
+    ($($I2CX:ident: $idx:expr,)+) => {
+        $(
+            impl Instance for $I2CX {
+                const IDX: u8 = $idx;
+
+                fn ptr() -> *const i2c_base::RegisterBlock {
+                    $I2CX::ptr() as *const _
+                }
+            }
+        )+
+    }
+}
+
+i2c_instance!(
+    I2CA: 0,
+    I2CB: 1,
+);
+
 pub struct I2cBase<I2C> {
     i2c: I2C,
     sys_clk: Hertz,
@@ -392,6 +444,22 @@ pub struct I2cMaster<I2C, ADDR = SevenBitAddress> {
     _addr: PhantomData<ADDR>,
 }
 
+impl<I2C: Instance, ADDR> I2cMaster<I2C, ADDR> {
+    /// Route the master interrupt of the I2C peripheral to the given NVIC interrupt using the
+    /// IRQSEL peripheral. This also activates the peripheral clock for IRQSEL.
+    ///
+    /// The NVIC interrupt is not unmasked, this needs to be done by the caller
+    pub fn route_irq(&mut self, syscfg: &mut SYSCONFIG, irqsel: &mut IRQSEL, interrupt: Interrupt) {
+        enable_peripheral_clock(syscfg, PeripheralClocks::Irqsel);
+        irqsel.i2c_ms[I2C::IDX as usize].write(|w| unsafe { w.bits(interrupt as u32) });
+    }
+
+    /// Remove the interrupt routing of the I2C master
+    pub fn unroute_irq(&mut self, irqsel: &mut IRQSEL) {
+        irqsel.i2c_ms[I2C::IDX as usize].write(|w| unsafe { w.bits(IRQ_DST_NONE) });
+    }
+}
+
 macro_rules! i2c_master {
     ($($I2CX:ident: ($i2cx:ident, $clk_enb:path),)+) => {
         $(
@@ -3,7 +3,10 @@
 pub use va108xx;
 pub use va108xx as pac;
 
+pub mod boot_memory;
 pub mod clock;
+#[cfg(feature = "framing")]
+pub mod framing;
 pub mod gpio;
 pub mod i2c;
 pub mod prelude;
@@ -14,6 +17,8 @@ pub mod timer;
 pub mod typelevel;
 pub mod uart;
 pub mod utility;
+#[cfg(feature = "async")]
+mod waker;
 
 mod private {
     /// Super trait used to mark traits with an exhaustive set of
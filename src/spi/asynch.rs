@@ -0,0 +1,147 @@
+//! # Async SPI support
+//!
+//! With the `async` feature, [`SpiBase`] and [`Spi`] implement the [`embedded_hal_async`]
+//! [`SpiBus`] trait. The FIFOs are serviced in the same way as for the [`IrqSpi`](super::IrqSpi):
+//! at most [`FIFO_DEPTH`] words are in flight and the task waits for the RX FIFO half full and
+//! RX timeout interrupts. The task is woken from [`on_interrupt_async`], which needs to be
+//! called in the interrupt handler the SPI interrupt was routed to with
+//! [`route_irq`](SpiBase::route_irq).
+//!
+//! Like for the blocking [`SpiBus`](embedded_hal_1::spi::SpiBus) implementation, the hardware
+//! chip select stays asserted for the duration of every operation in block mode.
+use core::future::poll_fn;
+use core::task::Poll;
+
+use embedded_hal_async::spi::SpiBus;
+
+use super::{
+    transfer::{InPlaceBuffer, SplitBuffers, TransferBuffers},
+    Instance, Spi, SpiBase, Word, FIFO_DEPTH,
+};
+use crate::waker::IrqWaker;
+
+static WAKERS: [IrqWaker; 3] = [IrqWaker::new(), IrqWaker::new(), IrqWaker::new()];
+
+/// This function should be called in the interrupt handler the SPI interrupt was routed to if
+/// the async API is used. It masks the SPI interrupts again and wakes the waiting task
+pub fn on_interrupt_async<SPI: Instance>() {
+    unsafe { &(*SPI::ptr()) }.irq_enb.reset();
+    WAKERS[SPI::IDX as usize].wake();
+}
+
+impl<SPI: Instance, WORD: Word> SpiBase<SPI, WORD> {
+    async fn transfer_async(&mut self, mut bufs: impl TransferBuffers<WORD>) {
+        let len = bufs.len();
+        if len == 0 {
+            return;
+        }
+        // Every completed operation leaves the FIFOs empty. Words can only be left over if a
+        // previous operation was cancelled by dropping its future
+        self.spi.fifo_clr.write(|w| {
+            w.rxfifo().set_bit();
+            w.txfifo().set_bit()
+        });
+        self.spi
+            .rxfifoirqtrg
+            .write(|w| unsafe { w.bits(FIFO_DEPTH as u32 / 2) });
+        if self.blockmode {
+            self.spi.ctrl1.modify(|_, w| w.bmstall().set_bit());
+        }
+        let mut tx_idx = 0;
+        let mut rx_idx = 0;
+        poll_fn(|cx| {
+            // The timeout is cleared before the RX FIFO is emptied, so words which are received
+            // afterwards always trigger a new timeout
+            self.spi.irq_clr.write(|w| w.rtim().set_bit());
+            while rx_idx < tx_idx && self.spi.status.read().rne().bit_is_set() {
+                bufs.store_rx_word(rx_idx, WORD::from_data(self.spi.data.read().bits()));
+                rx_idx += 1;
+            }
+            while tx_idx < len
+                && tx_idx - rx_idx < FIFO_DEPTH
+                && self.spi.status.read().tnf().bit_is_set()
+            {
+                let word = bufs.tx_word(tx_idx);
+                self.spi.data.write(|w| unsafe { w.bits(word.to_data()) });
+                tx_idx += 1;
+            }
+            if rx_idx == len {
+                return Poll::Ready(());
+            }
+            WAKERS[SPI::IDX as usize].register(cx.waker());
+            self.spi.irq_enb.write(|w| {
+                w.rtim().set_bit();
+                w.rxim().set_bit()
+            });
+            Poll::Pending
+        })
+        .await;
+        if self.blockmode {
+            self.delay_ns(self.timing.cs_hold_ns);
+            // Clearing the stall bit ends the frame and deasserts the chip select
+            self.spi.ctrl1.modify(|_, w| w.bmstall().clear_bit());
+        }
+    }
+}
+
+impl<SPI: Instance, WORD: Word + 'static> SpiBus<WORD> for SpiBase<SPI, WORD> {
+    async fn read(&mut self, words: &mut [WORD]) -> Result<(), Self::Error> {
+        self.transfer_async(SplitBuffers {
+            read: words,
+            write: &[],
+            fill: self.fill_word,
+        })
+        .await;
+        Ok(())
+    }
+
+    async fn write(&mut self, words: &[WORD]) -> Result<(), Self::Error> {
+        self.transfer_async(SplitBuffers {
+            read: &mut [],
+            write: words,
+            fill: self.fill_word,
+        })
+        .await;
+        Ok(())
+    }
+
+    async fn transfer(&mut self, read: &mut [WORD], write: &[WORD]) -> Result<(), Self::Error> {
+        let fill = self.fill_word;
+        self.transfer_async(SplitBuffers { read, write, fill })
+            .await;
+        Ok(())
+    }
+
+    async fn transfer_in_place(&mut self, words: &mut [WORD]) -> Result<(), Self::Error> {
+        self.transfer_async(InPlaceBuffer(words)).await;
+        Ok(())
+    }
+
+    /// All operations only complete once the last word was received, so there is nothing
+    /// left to wait for
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl<SPI: Instance, PINS, WORD: Word + 'static> SpiBus<WORD> for Spi<SPI, PINS, WORD> {
+    async fn read(&mut self, words: &mut [WORD]) -> Result<(), Self::Error> {
+        SpiBus::read(&mut self.spi_base, words).await
+    }
+
+    async fn write(&mut self, words: &[WORD]) -> Result<(), Self::Error> {
+        SpiBus::write(&mut self.spi_base, words).await
+    }
+
+    async fn transfer(&mut self, read: &mut [WORD], write: &[WORD]) -> Result<(), Self::Error> {
+        SpiBus::transfer(&mut self.spi_base, read, write).await
+    }
+
+    async fn transfer_in_place(&mut self, words: &mut [WORD]) -> Result<(), Self::Error> {
+        SpiBus::transfer_in_place(&mut self.spi_base, words).await
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        SpiBus::flush(&mut self.spi_base).await
+    }
+}
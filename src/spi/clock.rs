@@ -0,0 +1,262 @@
+//! # SPI clock calculation
+//!
+//! In master mode, the SPI clock is derived from the system clock with two dividers:
+//!
+//! `spi_clk = sys_clk / (CLKPRESCALE * (SCRDV + 1))`
+//!
+//! CLKPRESCALE is an even value between [`MIN_CLK_PRESCALE`] and [`MAX_CLK_PRESCALE`] and SCRDV
+//! is an 8 bit value. The functions in this module only use integer arithmetic, do not access
+//! any hardware and can be used and tested on the host as well.
+use crate::time::Hertz;
+
+/// Smallest value of the CLKPRESCALE register
+pub const MIN_CLK_PRESCALE: u8 = 2;
+/// Largest value of the CLKPRESCALE register. The lowest bit is ignored by the hardware, so
+/// only even values can be used
+pub const MAX_CLK_PRESCALE: u8 = 254;
+
+/// Policy which is used if the requested SPI clock can not be generated exactly
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub enum ClockPolicy {
+    /// Select the divisor which yields the clock closest to the requested clock. The achieved
+    /// clock might be faster than the requested clock
+    #[default]
+    Nearest,
+    /// Select the fastest clock which is not faster than the requested clock. This is useful
+    /// if the requested clock is the maximum clock supported by a device
+    NotFaster,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ClockError {
+    /// The requested SPI clock is 0 or can not be generated from the system clock with the
+    /// given policy
+    Unreachable,
+}
+
+/// Result of a SPI clock calculation
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct ClockDivisor {
+    /// Value of the CLKPRESCALE register
+    pub clk_prescale: u8,
+    /// Value of the SCRDV field of the CTRL0 register
+    pub scrdv: u8,
+    /// SPI clock which is actually achieved with this divisor
+    pub achieved_clk: Hertz,
+}
+
+impl ClockDivisor {
+    /// Build the divisor from given register values
+    pub fn from_regs(sys_clk: Hertz, clk_prescale: u8, scrdv: u8) -> Self {
+        ClockDivisor {
+            clk_prescale,
+            scrdv,
+            achieved_clk: Hertz(achieved_clk(
+                sys_clk.0 as u64,
+                total_divisor(clk_prescale, scrdv),
+            )),
+        }
+    }
+
+    /// Total divisor between the system clock and the SPI clock
+    #[inline]
+    pub fn divisor(&self) -> u32 {
+        total_divisor(self.clk_prescale, self.scrdv) as u32
+    }
+}
+
+/// Calculate the CLKPRESCALE and SCRDV values for a given system clock and target SPI clock.
+///
+/// Returns [`ClockError::Unreachable`] if the SPI clock is 0 or lies outside of the range which
+/// can be generated from the system clock. With [`ClockPolicy::NotFaster`], a SPI clock above
+/// the fastest achievable clock is accepted and the fastest clock is returned
+pub fn calculate_clock_divisor(
+    sys_clk: Hertz,
+    spi_clk: Hertz,
+    policy: ClockPolicy,
+) -> Result<ClockDivisor, ClockError> {
+    if spi_clk.0 == 0 || sys_clk.0 == 0 {
+        return Err(ClockError::Unreachable);
+    }
+    let sys_clk_val = sys_clk.0 as u64;
+    let target = spi_clk.0 as u64;
+    let min_divisor = total_divisor(MIN_CLK_PRESCALE, 0);
+    let max_divisor = total_divisor(MAX_CLK_PRESCALE, u8::MAX);
+    if policy == ClockPolicy::Nearest
+        && (target * min_divisor > sys_clk_val || target * max_divisor < sys_clk_val)
+    {
+        return Err(ClockError::Unreachable);
+    }
+    let mut best: Option<(ClockDivisor, u64)> = None;
+    for clk_prescale in (MIN_CLK_PRESCALE..=MAX_CLK_PRESCALE).step_by(2) {
+        // SCRDV + 1 values just below and above the ideal divider
+        let lower = sys_clk_val / (target * clk_prescale as u64);
+        for scrdv_plus_one in [lower, lower + 1] {
+            let scrdv_plus_one = scrdv_plus_one.clamp(1, u8::MAX as u64 + 1);
+            let divisor = clk_prescale as u64 * scrdv_plus_one;
+            if policy == ClockPolicy::NotFaster && sys_clk_val > target * divisor {
+                continue;
+            }
+            let achieved = achieved_clk(sys_clk_val, divisor) as u64;
+            let diff = achieved.abs_diff(target);
+            if matches!(best, Some((_, best_diff)) if best_diff <= diff) {
+                continue;
+            }
+            best = Some((
+                ClockDivisor {
+                    clk_prescale,
+                    scrdv: (scrdv_plus_one - 1) as u8,
+                    achieved_clk: Hertz(achieved as u32),
+                },
+                diff,
+            ));
+        }
+    }
+    best.map(|(divisor, _)| divisor)
+        .ok_or(ClockError::Unreachable)
+}
+
+#[inline]
+fn total_divisor(clk_prescale: u8, scrdv: u8) -> u64 {
+    clk_prescale as u64 * (scrdv as u64 + 1)
+}
+
+/// SPI clock for a total divisor, rounded to the nearest value
+#[inline]
+fn achieved_clk(sys_clk: u64, divisor: u64) -> u32 {
+    ((sys_clk + divisor / 2) / divisor) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SYS_CLK: Hertz = Hertz(50_000_000);
+
+    fn divisor(spi_clk: u32, policy: ClockPolicy) -> Result<(u8, u8, u32), ClockError> {
+        calculate_clock_divisor(SYS_CLK, Hertz(spi_clk), policy)
+            .map(|div| (div.clk_prescale, div.scrdv, div.achieved_clk.0))
+    }
+
+    #[test]
+    fn exact_divisor() {
+        for policy in [ClockPolicy::Nearest, ClockPolicy::NotFaster] {
+            // The smallest CLKPRESCALE value is preferred for equal divisors
+            assert_eq!(divisor(12_500_000, policy), Ok((2, 1, 12_500_000)));
+            assert_eq!(divisor(1_000_000, policy), Ok((2, 24, 1_000_000)));
+            assert_eq!(divisor(100_000, policy), Ok((2, 249, 100_000)));
+            // Requires a larger CLKPRESCALE value because SCRDV is limited to 8 bits
+            assert_eq!(divisor(10_000, policy), Ok((20, 249, 10_000)));
+        }
+    }
+
+    #[test]
+    fn nearest_rounds_to_closest_divisor() {
+        // The ideal divisor 5.56 lies between the divisors 4 and 6
+        assert_eq!(
+            divisor(9_000_000, ClockPolicy::Nearest),
+            Ok((2, 2, 8_333_333))
+        );
+        // The ideal divisor 4.55 is closer to 4, which yields a faster clock
+        assert_eq!(
+            divisor(11_000_000, ClockPolicy::Nearest),
+            Ok((2, 1, 12_500_000))
+        );
+        // The midpoint between 8.33 MHz and 12.5 MHz is 10.4166 MHz
+        assert_eq!(
+            divisor(10_416_667, ClockPolicy::Nearest),
+            Ok((2, 1, 12_500_000))
+        );
+        assert_eq!(
+            divisor(10_416_666, ClockPolicy::Nearest),
+            Ok((2, 2, 8_333_333))
+        );
+    }
+
+    #[test]
+    fn not_faster_rounds_down() {
+        assert_eq!(
+            divisor(9_000_000, ClockPolicy::NotFaster),
+            Ok((2, 2, 8_333_333))
+        );
+        assert_eq!(
+            divisor(11_000_000, ClockPolicy::NotFaster),
+            Ok((2, 2, 8_333_333))
+        );
+        assert_eq!(
+            divisor(12_499_999, ClockPolicy::NotFaster),
+            Ok((2, 2, 8_333_333))
+        );
+        assert_eq!(
+            divisor(12_500_000, ClockPolicy::NotFaster),
+            Ok((2, 1, 12_500_000))
+        );
+    }
+
+    #[test]
+    fn min_prescaler_and_scrdv() {
+        for policy in [ClockPolicy::Nearest, ClockPolicy::NotFaster] {
+            assert_eq!(
+                divisor(25_000_000, policy),
+                Ok((MIN_CLK_PRESCALE, 0, 25_000_000))
+            );
+        }
+    }
+
+    #[test]
+    fn max_prescaler_and_scrdv() {
+        // The slowest clock is 50 MHz / (254 * 256) = 768.9 Hz
+        for policy in [ClockPolicy::Nearest, ClockPolicy::NotFaster] {
+            assert_eq!(divisor(769, policy), Ok((MAX_CLK_PRESCALE, u8::MAX, 769)));
+        }
+    }
+
+    #[test]
+    fn too_fast() {
+        assert_eq!(
+            divisor(25_000_001, ClockPolicy::Nearest),
+            Err(ClockError::Unreachable)
+        );
+        assert_eq!(
+            divisor(40_000_000, ClockPolicy::Nearest),
+            Err(ClockError::Unreachable)
+        );
+        // The fastest clock is selected instead
+        assert_eq!(
+            divisor(40_000_000, ClockPolicy::NotFaster),
+            Ok((MIN_CLK_PRESCALE, 0, 25_000_000))
+        );
+    }
+
+    #[test]
+    fn too_slow() {
+        for policy in [ClockPolicy::Nearest, ClockPolicy::NotFaster] {
+            assert_eq!(divisor(768, policy), Err(ClockError::Unreachable));
+            assert_eq!(divisor(1, policy), Err(ClockError::Unreachable));
+        }
+    }
+
+    #[test]
+    fn zero_clocks() {
+        for policy in [ClockPolicy::Nearest, ClockPolicy::NotFaster] {
+            assert_eq!(divisor(0, policy), Err(ClockError::Unreachable));
+            assert_eq!(
+                calculate_clock_divisor(Hertz(0), Hertz(1_000_000), policy),
+                Err(ClockError::Unreachable)
+            );
+        }
+    }
+
+    #[test]
+    fn divisor_from_regs() {
+        let div = ClockDivisor::from_regs(SYS_CLK, 4, 9);
+        assert_eq!(div.divisor(), 40);
+        assert_eq!(div.achieved_clk, Hertz(1_250_000));
+        assert_eq!(
+            calculate_clock_divisor(SYS_CLK, Hertz(1_250_000), ClockPolicy::Nearest)
+                .unwrap()
+                .divisor(),
+            40
+        );
+    }
+}
@@ -0,0 +1,192 @@
+//! # embedded-hal 1.0 SPI support
+//!
+//! [`SpiBase`] and [`Spi`] implement the embedded-hal 1.0 [`SpiBus`] trait. The bus does not
+//! select a device, but in block mode the hardware chip select of the last applied transfer
+//! configuration stays asserted for the duration of every operation.
+//!
+//! The [`SpiDevice`] owns a [`SpiBase`] together with the [`TransferConfig`] of one device.
+//! Every [`transaction`](embedded_hal_1::spi::SpiDevice::transaction) applies the clock and mode
+//! of the device and keeps the hardware chip select [`HwCs::CS_ID`](super::HwCs::CS_ID)
+//! asserted for the full transaction, including [`Operation::DelayNs`] operations. This is done
+//! with the block mode stall feature of the peripheral, which keeps the chip select asserted
+//! while the TX FIFO is empty. Delay operations at the start of a transaction are performed
+//! before the chip select is asserted, because the chip select is only asserted once the first
+//! word is written. The chip select hold time of the [`TimingConfig`](super::TimingConfig) is
+//! inserted before the frame ends.
+use core::convert::Infallible;
+
+use embedded_hal_1::spi::{self, ErrorKind, ErrorType, Operation, SpiBus};
+
+use super::{
+    transfer::{InPlaceBuffer, SplitBuffers},
+    ClockError, Instance, OptionalHwCs, Spi, SpiBase, TransferConfig, Word,
+};
+
+impl<SPI, WORD> ErrorType for SpiBase<SPI, WORD> {
+    type Error = Infallible;
+}
+
+impl<SPI: Instance, WORD: Word + 'static> SpiBus<WORD> for SpiBase<SPI, WORD> {
+    fn read(&mut self, words: &mut [WORD]) -> Result<(), Self::Error> {
+        self.transfer_buffers(words, &[]);
+        Ok(())
+    }
+
+    fn write(&mut self, words: &[WORD]) -> Result<(), Self::Error> {
+        self.transfer_buffers(&mut [], words);
+        Ok(())
+    }
+
+    fn transfer(&mut self, read: &mut [WORD], write: &[WORD]) -> Result<(), Self::Error> {
+        self.transfer_buffers(read, write);
+        Ok(())
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [WORD]) -> Result<(), Self::Error> {
+        self.in_block_frame(|spi| spi.transfer_words(InPlaceBuffer(words)));
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.wait_idle();
+        Ok(())
+    }
+}
+
+impl<SPI, PINS, WORD> ErrorType for Spi<SPI, PINS, WORD> {
+    type Error = Infallible;
+}
+
+impl<SPI: Instance, PINS, WORD: Word + 'static> SpiBus<WORD> for Spi<SPI, PINS, WORD> {
+    fn read(&mut self, words: &mut [WORD]) -> Result<(), Self::Error> {
+        SpiBus::read(&mut self.spi_base, words)
+    }
+
+    fn write(&mut self, words: &[WORD]) -> Result<(), Self::Error> {
+        SpiBus::write(&mut self.spi_base, words)
+    }
+
+    fn transfer(&mut self, read: &mut [WORD], write: &[WORD]) -> Result<(), Self::Error> {
+        SpiBus::transfer(&mut self.spi_base, read, write)
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [WORD]) -> Result<(), Self::Error> {
+        SpiBus::transfer_in_place(&mut self.spi_base, words)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        SpiBus::flush(&mut self.spi_base)
+    }
+}
+
+//==================================================================================================
+// SPI device
+//==================================================================================================
+
+/// SPI device with exclusive access to the SPI bus which uses a hardware chip select
+pub struct SpiDevice<SPI, HWCS, WORD = u8> {
+    spi: SpiBase<SPI, WORD>,
+    transfer_cfg: TransferConfig<HWCS>,
+}
+
+impl<SPI: Instance, HWCS: OptionalHwCs<SPI>, WORD: Word> SpiDevice<SPI, HWCS, WORD> {
+    /// Create a new SPI device. The chip select of the transfer configuration is asserted
+    /// for every transaction. If no chip select is specified, only the clock and mode are
+    /// applied
+    pub fn new(spi: SpiBase<SPI, WORD>, transfer_cfg: TransferConfig<HWCS>) -> Self {
+        SpiDevice { spi, transfer_cfg }
+    }
+
+    #[inline]
+    pub fn transfer_cfg(&self) -> &TransferConfig<HWCS> {
+        &self.transfer_cfg
+    }
+
+    pub fn release(self) -> (SpiBase<SPI, WORD>, TransferConfig<HWCS>) {
+        (self.spi, self.transfer_cfg)
+    }
+}
+
+/// Execute a transaction on the bus. The hardware chip select of the transfer configuration
+/// is kept asserted until all operations were performed. No operation is performed if the
+/// SPI clock of the transfer configuration can not be generated
+pub(super) fn transaction<SPI: Instance, HWCS: OptionalHwCs<SPI>, WORD: Word>(
+    spi: &mut SpiBase<SPI, WORD>,
+    transfer_cfg: &TransferConfig<HWCS>,
+    operations: &mut [Operation<'_, WORD>],
+) -> Result<(), ClockError> {
+    spi.cfg_transfer(transfer_cfg)?;
+    run_operations(spi, transfer_cfg, operations);
+    Ok(())
+}
+
+/// Perform the operations of a transaction without applying the transfer configuration first.
+/// The bus must already be configured for the device
+pub(super) fn run_operations<SPI: Instance, HWCS: OptionalHwCs<SPI>, WORD: Word>(
+    spi: &mut SpiBase<SPI, WORD>,
+    transfer_cfg: &TransferConfig<HWCS>,
+    operations: &mut [Operation<'_, WORD>],
+) {
+    let hw_cs = transfer_cfg.hw_cs.is_some() && !transfer_cfg.sod;
+    let blockmode = spi.blockmode;
+    if hw_cs {
+        // The transfers need to know that block mode is used, so the FIFO is preloaded and
+        // the words of the frame are sent without gaps
+        spi.blockmode = true;
+        spi.spi.ctrl1.modify(|_, w| {
+            w.blockmode().set_bit();
+            w.bmstall().set_bit()
+        });
+    }
+    let fill = spi.fill_word;
+    for operation in operations {
+        match operation {
+            Operation::Read(read) => spi.transfer_words(SplitBuffers {
+                read,
+                write: &[],
+                fill,
+            }),
+            Operation::Write(write) => spi.transfer_words(SplitBuffers {
+                read: &mut [],
+                write,
+                fill,
+            }),
+            Operation::Transfer(read, write) => {
+                spi.transfer_words(SplitBuffers { read, write, fill })
+            }
+            Operation::TransferInPlace(words) => spi.transfer_words(InPlaceBuffer(words)),
+            Operation::DelayNs(ns) => {
+                spi.wait_idle();
+                spi.delay_ns(*ns);
+            }
+        }
+    }
+    spi.wait_idle();
+    if hw_cs {
+        spi.delay_ns(spi.timing.cs_hold_ns);
+        // Clearing the stall bit ends the frame and deasserts the chip select
+        spi.spi.ctrl1.modify(|_, w| {
+            w.bmstall().clear_bit();
+            w.blockmode().bit(blockmode)
+        });
+        spi.blockmode = blockmode;
+    }
+}
+
+impl spi::Error for ClockError {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+impl<SPI, HWCS, WORD> ErrorType for SpiDevice<SPI, HWCS, WORD> {
+    type Error = ClockError;
+}
+
+impl<SPI: Instance, HWCS: OptionalHwCs<SPI>, WORD: Word + 'static> spi::SpiDevice<WORD>
+    for SpiDevice<SPI, HWCS, WORD>
+{
+    fn transaction(&mut self, operations: &mut [Operation<'_, WORD>]) -> Result<(), Self::Error> {
+        transaction(&mut self.spi, &self.transfer_cfg, operations)
+    }
+}
@@ -0,0 +1,236 @@
+//! # Interrupt-driven SPI transfers
+//!
+//! The [`IrqSpi`] wraps a [`SpiBase`] and performs master transfers from user-provided static
+//! buffers in the background. [`start_transfer`](IrqSpi::start_transfer) fills the TX FIFO and
+//! the remaining words are written and received inside
+//! [`on_interrupt`](IrqSpi::on_interrupt), which needs to be called in the interrupt handler
+//! the SPI interrupt was routed to with [`route_irq`](IrqSpi::route_irq).
+//!
+//! At most [`FIFO_DEPTH`] words are in flight at any time, so the RX FIFO can never overflow.
+//! The RX FIFO half full interrupt is used while more words are outstanding and the RX timeout
+//! interrupt signals the reception of the last words.
+//!
+//! The completion can be polled with [`transfer_completed`](IrqSpi::transfer_completed) or
+//! signalled with a callback which is called from the interrupt handler. The [`IrqSpi`] is
+//! usually shared between the interrupt handler and the application by placing it inside a
+//! [`cortex_m::interrupt::Mutex`].
+use core::convert::Infallible;
+
+use crate::{
+    pac::{Interrupt, IRQSEL, SYSCONFIG},
+    time::Hertz,
+};
+
+use super::{ClockError, Instance, OptionalHwCs, SpiBase, TransferConfig, Word, FIFO_DEPTH};
+
+/// Callback which is called from the interrupt handler with the number of transferred words
+/// once a transfer is completed
+pub type TransferCallback = fn(usize);
+
+struct MasterTransfer<WORD: 'static> {
+    tx: &'static [WORD],
+    rx: &'static mut [WORD],
+    /// Total number of words which are clocked out
+    len: usize,
+    tx_idx: usize,
+    rx_idx: usize,
+    completed: bool,
+    cancelled: bool,
+}
+
+/// SPI master which performs interrupt-driven transfers
+pub struct IrqSpi<SPI, WORD: 'static = u8> {
+    spi: SpiBase<SPI, WORD>,
+    transfer: Option<MasterTransfer<WORD>>,
+    callback: Option<TransferCallback>,
+}
+
+impl<SPI: Instance, WORD: Word> IrqSpi<SPI, WORD> {
+    pub fn new(spi: SpiBase<SPI, WORD>) -> Self {
+        IrqSpi {
+            spi,
+            transfer: None,
+            callback: None,
+        }
+    }
+
+    /// Set the callback which is called once a transfer is completed. The callback is not
+    /// called for cancelled transfers
+    pub fn set_callback(&mut self, callback: Option<TransferCallback>) {
+        self.callback = callback;
+    }
+
+    /// Apply a transfer configuration. This should only be done while no transfer is in progress
+    pub fn cfg_transfer<HWCS: OptionalHwCs<SPI>>(
+        &mut self,
+        transfer_cfg: &TransferConfig<HWCS>,
+    ) -> Result<Hertz, ClockError> {
+        self.spi.cfg_transfer(transfer_cfg)
+    }
+
+    /// Route the SPI interrupt to the given NVIC interrupt using the IRQSEL peripheral. The NVIC
+    /// interrupt needs to be unmasked by the caller
+    pub fn route_irq(&mut self, syscfg: &mut SYSCONFIG, irqsel: &mut IRQSEL, interrupt: Interrupt) {
+        self.spi.route_irq(syscfg, irqsel, interrupt);
+    }
+
+    /// Remove the interrupt routing of the SPI peripheral
+    pub fn unroute_irq(&mut self, irqsel: &mut IRQSEL) {
+        self.spi.unroute_irq(irqsel);
+    }
+
+    /// Start an interrupt-driven transfer. The words of the TX buffer are written and the
+    /// received words are stored in the RX buffer. If the RX buffer is longer, the
+    /// [fill word](SpiBase::set_fill_word) is written.
+    /// If the TX buffer is longer, the surplus received words are discarded. A transfer which is
+    /// still in progress is cancelled.
+    ///
+    /// If block mode is enabled, the chip select stays asserted for the whole transfer
+    pub fn start_transfer(&mut self, tx: &'static [WORD], rx: &'static mut [WORD]) {
+        self.cancel_transfer();
+        self.spi.spi.fifo_clr.write(|w| {
+            w.rxfifo().set_bit();
+            w.txfifo().set_bit()
+        });
+        self.spi.spi.irq_clr.write(|w| {
+            w.rorim().set_bit();
+            w.rtim().set_bit()
+        });
+        let len = core::cmp::max(tx.len(), rx.len());
+        let mut transfer = MasterTransfer {
+            tx,
+            rx,
+            len,
+            tx_idx: 0,
+            rx_idx: 0,
+            completed: len == 0,
+            cancelled: false,
+        };
+        if transfer.completed {
+            self.transfer = Some(transfer);
+            return;
+        }
+        if self.spi.blockmode {
+            // The stall bit keeps the chip select asserted if the interrupt handler can not
+            // refill the TX FIFO in time
+            self.spi.spi.ctrl1.modify(|_, w| w.bmstall().set_bit());
+        }
+        self.spi
+            .spi
+            .rxfifoirqtrg
+            .write(|w| unsafe { w.bits(FIFO_DEPTH as u32 / 2) });
+        fill_tx_fifo(&self.spi, &mut transfer);
+        self.transfer = Some(transfer);
+        self.spi.spi.irq_enb.write(|w| {
+            w.rtim().set_bit();
+            w.rxim().set_bit()
+        });
+    }
+
+    /// This function should be called in the interrupt handler the SPI interrupt was routed to.
+    ///
+    /// Returns the number of transferred words once the transfer is completed and
+    /// [nb::Error::WouldBlock] while it is still in progress
+    pub fn on_interrupt(&mut self) -> nb::Result<usize, Infallible> {
+        let transfer = match self.transfer.as_mut() {
+            Some(transfer) if !transfer.completed && !transfer.cancelled => transfer,
+            _ => return Err(nb::Error::WouldBlock),
+        };
+        let spi = &self.spi.spi;
+        while spi.status.read().rne().bit_is_set() {
+            let word = WORD::from_data(spi.data.read().bits());
+            if let Some(slot) = transfer.rx.get_mut(transfer.rx_idx) {
+                *slot = word;
+            }
+            transfer.rx_idx += 1;
+        }
+        spi.irq_clr.write(|w| w.rtim().set_bit());
+        fill_tx_fifo(&self.spi, transfer);
+        if transfer.rx_idx < transfer.len {
+            return Err(nb::Error::WouldBlock);
+        }
+        transfer.completed = true;
+        let len = transfer.len;
+        self.stop();
+        if let Some(callback) = self.callback {
+            callback(len);
+        }
+        Ok(len)
+    }
+
+    /// Returns [true] if the interrupt-driven transfer was completed. The buffers can then be
+    /// retrieved with [`take_transfer`](Self::take_transfer)
+    pub fn transfer_completed(&self) -> bool {
+        matches!(&self.transfer, Some(transfer) if transfer.completed)
+    }
+
+    /// Returns [true] if a transfer is in progress
+    pub fn transfer_in_progress(&self) -> bool {
+        matches!(&self.transfer, Some(transfer) if !transfer.completed && !transfer.cancelled)
+    }
+
+    /// Returns [true] if the last transfer was cancelled
+    pub fn transfer_cancelled(&self) -> bool {
+        matches!(&self.transfer, Some(transfer) if transfer.cancelled)
+    }
+
+    /// Cancel the transfer which is in progress. Words which are still in the TX FIFO are
+    /// discarded. The buffers can be retrieved with [`take_transfer`](Self::take_transfer)
+    /// afterwards. Returns [true] if a transfer was cancelled
+    pub fn cancel_transfer(&mut self) -> bool {
+        if !self.transfer_in_progress() {
+            return false;
+        }
+        self.stop();
+        self.spi.spi.fifo_clr.write(|w| {
+            w.rxfifo().set_bit();
+            w.txfifo().set_bit()
+        });
+        if let Some(transfer) = self.transfer.as_mut() {
+            transfer.cancelled = true;
+        }
+        true
+    }
+
+    /// Return the buffers and the number of words which were stored in the RX buffer for the
+    /// current interrupt-driven transfer. A transfer which is still in progress is cancelled.
+    /// Returns [None] if no transfer was started
+    pub fn take_transfer(&mut self) -> Option<(&'static [WORD], &'static mut [WORD], usize)> {
+        self.cancel_transfer();
+        self.transfer.take().map(|transfer| {
+            let received = core::cmp::min(transfer.rx_idx, transfer.rx.len());
+            (transfer.tx, transfer.rx, received)
+        })
+    }
+
+    /// Cancel a transfer in progress and release the SPI peripheral
+    pub fn release(mut self) -> SpiBase<SPI, WORD> {
+        self.cancel_transfer();
+        self.spi
+    }
+
+    /// Disable the interrupts and end the block mode frame
+    fn stop(&mut self) {
+        let spi = &self.spi.spi;
+        spi.irq_enb.reset();
+        if self.spi.blockmode {
+            spi.ctrl1.modify(|_, w| w.bmstall().clear_bit());
+        }
+    }
+}
+
+/// Write words to the TX FIFO while less than [`FIFO_DEPTH`] words are in flight
+fn fill_tx_fifo<SPI: Instance, WORD: Word>(
+    spi: &SpiBase<SPI, WORD>,
+    transfer: &mut MasterTransfer<WORD>,
+) {
+    let fill = spi.fill_word;
+    while transfer.tx_idx < transfer.len
+        && transfer.tx_idx - transfer.rx_idx < FIFO_DEPTH
+        && spi.spi.status.read().tnf().bit_is_set()
+    {
+        let word = transfer.tx.get(transfer.tx_idx).copied().unwrap_or(fill);
+        spi.spi.data.write(|w| unsafe { w.bits(word.to_data()) });
+        transfer.tx_idx += 1;
+    }
+}
@@ -0,0 +1,1017 @@
+//! API for the SPI peripheral
+//!
+//! ## Examples
+//!
+//! - [Blocking SPI example](https://github.com/robamu-org/va108xx-hal-rs/blob/main/examples/spi.rs)
+use crate::Sealed;
+use crate::{
+    clock::{enable_peripheral_clock, PeripheralClocks},
+    gpio::pins::{
+        AltFunc1, AltFunc2, AltFunc3, Pin, PA10, PA11, PA12, PA13, PA14, PA15, PA16, PA17, PA18,
+        PA19, PA20, PA21, PA22, PA23, PA24, PA25, PA26, PA27, PA28, PA29, PA30, PA31, PB0, PB1,
+        PB10, PB11, PB12, PB13, PB14, PB15, PB16, PB17, PB18, PB19, PB2, PB22, PB23, PB3, PB4, PB5,
+        PB6, PB7, PB8, PB9,
+    },
+    pac::{spia as spi_base, Interrupt, IRQSEL, SPIA, SPIB, SPIC, SYSCONFIG},
+    time::Hertz,
+};
+use core::{convert::Infallible, fmt::Debug, marker::PhantomData, ops::Deref};
+use embedded_hal::{
+    blocking,
+    spi::{FullDuplex, Mode, MODE_0, MODE_1, MODE_2, MODE_3},
+};
+
+mod clock;
+pub use clock::*;
+
+mod hal_1;
+pub use hal_1::*;
+
+mod irq;
+pub use irq::*;
+
+mod shared;
+pub use shared::*;
+
+mod slave;
+pub use slave::*;
+
+mod timing;
+pub use timing::*;
+
+mod transfer;
+use transfer::InPlaceBuffer;
+
+#[cfg(feature = "async")]
+mod asynch;
+#[cfg(feature = "async")]
+pub use asynch::*;
+
+const IRQ_DST_NONE: u32 = 0xffffffff;
+
+/// FIFO depth of the SPI peripheral in words
+const FIFO_DEPTH: usize = 16;
+
+//==================================================================================================
+// Defintions
+//==================================================================================================
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum HwChipSelectId {
+    Id0 = 0,
+    Id1 = 1,
+    Id2 = 2,
+    Id3 = 3,
+    Id4 = 4,
+    Id5 = 5,
+    Id6 = 6,
+    Id7 = 7,
+    Invalid = 0xff,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum WordSize {
+    OneBit = 0x00,
+    FourBits = 0x03,
+    FiveBits = 0x04,
+    SixBits = 0x05,
+    SevenBits = 0x06,
+    EightBits = 0x07,
+    NineBits = 0x08,
+    TenBits = 0x09,
+    ElevenBits = 0x0a,
+    TwelveBits = 0x0b,
+    ThirteenBits = 0x0c,
+    FourteenBits = 0x0d,
+    FifteenBits = 0x0e,
+    SixteenBits = 0x0f,
+}
+
+//==================================================================================================
+// Pin type definitions
+//==================================================================================================
+
+pub trait PinSck<SPI>: Sealed {}
+pub trait PinMosi<SPI>: Sealed {}
+pub trait PinMiso<SPI>: Sealed {}
+
+pub trait HwCs: Sealed {
+    const CS_ID: HwChipSelectId;
+}
+pub trait OptionalHwCs<SPI>: HwCs + Sealed {}
+
+macro_rules! hw_cs_pin {
+    ($SPIx:ident, $PXx:ident, $AFx:ident, $HwCsIdent:path, $typedef:ident) => {
+        impl HwCs for Pin<$PXx, $AFx> {
+            const CS_ID: HwChipSelectId = $HwCsIdent;
+        }
+        impl OptionalHwCs<$SPIx> for Pin<$PXx, $AFx> {}
+        pub type $typedef = Pin<$PXx, $AFx>;
+    };
+}
+
+impl HwCs for NoneT {
+    const CS_ID: HwChipSelectId = HwChipSelectId::Invalid;
+}
+
+impl OptionalHwCs<SPIA> for NoneT {}
+impl OptionalHwCs<SPIB> for NoneT {}
+
+// SPIA
+
+impl PinSck<SPIA> for Pin<PA31, AltFunc1> {}
+impl PinMosi<SPIA> for Pin<PA30, AltFunc1> {}
+impl PinMiso<SPIA> for Pin<PA29, AltFunc1> {}
+
+pub type SpiAPortASck = Pin<PA31, AltFunc1>;
+pub type SpiAPortAMosi = Pin<PA30, AltFunc1>;
+pub type SpiAPortAMiso = Pin<PA29, AltFunc1>;
+
+impl PinSck<SPIA> for Pin<PB9, AltFunc2> {}
+impl PinMosi<SPIA> for Pin<PB8, AltFunc2> {}
+impl PinMiso<SPIA> for Pin<PB7, AltFunc2> {}
+
+pub type SpiAPortBSck = Pin<PB9, AltFunc2>;
+pub type SpiAPortBMosi = Pin<PB8, AltFunc2>;
+pub type SpiAPortBMiso = Pin<PB7, AltFunc2>;
+
+hw_cs_pin!(SPIA, PA28, AltFunc1, HwChipSelectId::Id0, HwCs0SpiAPortA);
+hw_cs_pin!(SPIA, PA27, AltFunc1, HwChipSelectId::Id1, HwCs1SpiAPortA);
+hw_cs_pin!(SPIA, PA26, AltFunc1, HwChipSelectId::Id2, HwCs2SpiAPortA);
+hw_cs_pin!(SPIA, PA25, AltFunc1, HwChipSelectId::Id3, HwCs3SpiAPortA);
+hw_cs_pin!(SPIA, PA24, AltFunc1, HwChipSelectId::Id4, HwCs4SpiAPortA);
+hw_cs_pin!(SPIA, PA23, AltFunc1, HwChipSelectId::Id5, HwCs5SpiAPortA);
+hw_cs_pin!(SPIA, PA22, AltFunc1, HwChipSelectId::Id6, HwCs6SpiAPortA);
+hw_cs_pin!(SPIA, PA21, AltFunc1, HwChipSelectId::Id7, HwCs7SpiAPortA);
+
+hw_cs_pin!(SPIA, PB6, AltFunc2, HwChipSelectId::Id0, HwCs0SpiAPortB);
+hw_cs_pin!(SPIA, PB5, AltFunc2, HwChipSelectId::Id6, HwCs6SpiAPortB);
+hw_cs_pin!(SPIA, PB4, AltFunc2, HwChipSelectId::Id5, HwCs5SpiAPortB);
+hw_cs_pin!(SPIA, PB3, AltFunc2, HwChipSelectId::Id4, HwCs4SpiAPortB);
+hw_cs_pin!(SPIA, PB2, AltFunc2, HwChipSelectId::Id3, HwCs3SpiAPortB);
+hw_cs_pin!(SPIA, PB1, AltFunc2, HwChipSelectId::Id2, HwCs2SpiAPortB);
+hw_cs_pin!(SPIA, PB0, AltFunc2, HwChipSelectId::Id1, HwCs1SpiAPortB);
+
+// SPIB
+
+impl PinSck<SPIB> for Pin<PA20, AltFunc2> {}
+impl PinMosi<SPIB> for Pin<PA19, AltFunc2> {}
+impl PinMiso<SPIB> for Pin<PA18, AltFunc2> {}
+
+pub type SpiBPortASck = Pin<PA20, AltFunc2>;
+pub type SpiBPortAMosi = Pin<PA19, AltFunc2>;
+pub type SpiBPortAMiso = Pin<PA18, AltFunc2>;
+
+impl PinSck<SPIB> for Pin<PB19, AltFunc1> {}
+impl PinMosi<SPIB> for Pin<PB18, AltFunc1> {}
+impl PinMiso<SPIB> for Pin<PB17, AltFunc1> {}
+
+impl PinSck<SPIB> for Pin<PB5, AltFunc1> {}
+impl PinMosi<SPIB> for Pin<PB4, AltFunc1> {}
+impl PinMiso<SPIB> for Pin<PB3, AltFunc1> {}
+
+hw_cs_pin!(SPIB, PB16, AltFunc1, HwChipSelectId::Id0, HwCs0SpiBPortB0);
+hw_cs_pin!(SPIB, PB15, AltFunc1, HwChipSelectId::Id1, HwCs1SpiBPortB0);
+hw_cs_pin!(SPIB, PB14, AltFunc1, HwChipSelectId::Id2, HwCs2SpiBPortB0);
+hw_cs_pin!(SPIB, PB13, AltFunc1, HwChipSelectId::Id3, HwCs3SpiBPortB);
+hw_cs_pin!(SPIB, PB12, AltFunc1, HwChipSelectId::Id4, HwCs4SpiBPortB);
+hw_cs_pin!(SPIB, PB11, AltFunc1, HwChipSelectId::Id5, HwCs5SpiBPortB);
+hw_cs_pin!(SPIB, PB10, AltFunc1, HwChipSelectId::Id6, HwCs6SpiBPortB);
+
+hw_cs_pin!(SPIB, PB2, AltFunc1, HwChipSelectId::Id0, HwCs0SpiBPortB1);
+hw_cs_pin!(SPIB, PB1, AltFunc1, HwChipSelectId::Id1, HwCs1SpiBPortB1);
+hw_cs_pin!(SPIB, PB0, AltFunc1, HwChipSelectId::Id2, HwCs2SpiBPortB1);
+
+hw_cs_pin!(SPIB, PB12, AltFunc2, HwChipSelectId::Id0, HwCs0SpiBPortB2);
+hw_cs_pin!(SPIB, PB11, AltFunc2, HwChipSelectId::Id1, HwCs1SpiBPortB2);
+hw_cs_pin!(SPIB, PB10, AltFunc2, HwChipSelectId::Id2, HwCs2SpiBPortB2);
+
+hw_cs_pin!(SPIB, PA17, AltFunc2, HwChipSelectId::Id0, HwCs0SpiBPortA);
+hw_cs_pin!(SPIB, PA16, AltFunc2, HwChipSelectId::Id1, HwCs1SpiBPortA);
+hw_cs_pin!(SPIB, PA15, AltFunc2, HwChipSelectId::Id2, HwCs2SpiBPortA);
+hw_cs_pin!(SPIB, PA14, AltFunc2, HwChipSelectId::Id3, HwCs3SpiBPortA);
+hw_cs_pin!(SPIB, PA13, AltFunc2, HwChipSelectId::Id4, HwCs4SpiBPortA);
+hw_cs_pin!(SPIB, PA12, AltFunc2, HwChipSelectId::Id5, HwCs5SpiBPortA0);
+hw_cs_pin!(SPIB, PA11, AltFunc2, HwChipSelectId::Id6, HwCs6SpiBPortA0);
+hw_cs_pin!(SPIB, PA10, AltFunc2, HwChipSelectId::Id7, HwCs7SpiBPortA0);
+
+hw_cs_pin!(SPIB, PA23, AltFunc2, HwChipSelectId::Id5, HwCs5SpiBPortA1);
+hw_cs_pin!(SPIB, PA22, AltFunc2, HwChipSelectId::Id6, HwCs6SpiBPortA1);
+hw_cs_pin!(SPIB, PA21, AltFunc2, HwChipSelectId::Id7, HwCs7SpiBPortA1);
+
+// SPIC
+
+hw_cs_pin!(SPIC, PB9, AltFunc3, HwChipSelectId::Id1, HwCs1SpiCPortB0);
+hw_cs_pin!(SPIC, PB8, AltFunc3, HwChipSelectId::Id2, HwCs2SpiCPortB0);
+hw_cs_pin!(SPIC, PB7, AltFunc3, HwChipSelectId::Id3, HwCs3SpiCPortB);
+
+hw_cs_pin!(SPIC, PB22, AltFunc3, HwChipSelectId::Id1, HwCs1SpiCPortB1);
+hw_cs_pin!(SPIC, PB23, AltFunc3, HwChipSelectId::Id2, HwCs2SpiCPortB1);
+
+hw_cs_pin!(SPIC, PA20, AltFunc1, HwChipSelectId::Id1, HwCs1SpiCPortA0);
+hw_cs_pin!(SPIC, PA19, AltFunc1, HwChipSelectId::Id2, HwCs2SpiCPortA0);
+hw_cs_pin!(SPIC, PB18, AltFunc1, HwChipSelectId::Id3, HwCs3SpiCPortA0);
+
+hw_cs_pin!(SPIC, PA23, AltFunc3, HwChipSelectId::Id1, HwCs1SpiCPortA1);
+hw_cs_pin!(SPIC, PA22, AltFunc3, HwChipSelectId::Id2, HwCs2SpiCPortA1);
+hw_cs_pin!(SPIC, PA21, AltFunc3, HwChipSelectId::Id3, HwCs3SpiCPortA1);
+hw_cs_pin!(SPIC, PA20, AltFunc3, HwChipSelectId::Id4, HwCs4SpiCPortA);
+
+/// Chip select of the boot memory. SPIC is connected to the boot memory with dedicated ROM
+/// pins, which do not need to be configured, and the boot memory uses chip select 0
+pub struct RomCs;
+
+impl Sealed for RomCs {}
+impl HwCs for RomCs {
+    const CS_ID: HwChipSelectId = HwChipSelectId::Id0;
+}
+impl OptionalHwCs<SPIC> for RomCs {}
+
+//==================================================================================================
+// Config
+//==================================================================================================
+
+pub trait GenericTransferConfig {
+    fn sod(&mut self, sod: bool);
+    fn blockmode(&mut self, blockmode: bool);
+    fn mode(&mut self, mode: Mode);
+    fn frequency(&mut self, spi_clk: Hertz);
+    fn hw_cs_id(&self) -> u8;
+}
+
+/// This struct contains all configuration parameter which are transfer specific
+/// and might change for transfers to different SPI slaves
+#[derive(Copy, Clone)]
+pub struct TransferConfig<HWCS> {
+    pub spi_clk: Hertz,
+    pub mode: Mode,
+    /// This only works if the Slave Output Disable (SOD) bit of the [`SpiConfig`] is set to
+    /// false
+    pub hw_cs: Option<HWCS>,
+    pub sod: bool,
+    /// If this is enabled, all data in the FIFO is transmitted in a single frame unless
+    /// the BMSTOP bit is set on a dataword. A frame is defined as CSn being active for the
+    /// duration of multiple data words. Without block mode, the chip select is deasserted
+    /// between the words
+    pub blockmode: bool,
+    pub timing: TimingConfig,
+}
+
+/// Type erased variant of the transfer configuration. This is required to avoid generics in
+/// the SPI constructor.
+pub struct ReducedTransferConfig {
+    pub spi_clk: Hertz,
+    pub mode: Mode,
+    pub sod: bool,
+    /// If this is enabled, all data in the FIFO is transmitted in a single frame unless
+    /// the BMSTOP bit is set on a dataword. A frame is defined as CSn being active for the
+    /// duration of multiple data words
+    pub blockmode: bool,
+    pub hw_cs: HwChipSelectId,
+    pub timing: TimingConfig,
+}
+
+impl TransferConfig<NoneT> {
+    pub fn new_no_hw_cs(spi_clk: Hertz, mode: Mode, blockmode: bool, sod: bool) -> Self {
+        TransferConfig {
+            spi_clk,
+            mode,
+            hw_cs: None,
+            sod,
+            blockmode,
+            timing: TimingConfig::default(),
+        }
+    }
+}
+
+impl<HWCS> TransferConfig<HWCS> {
+    pub fn timing(mut self, timing: TimingConfig) -> Self {
+        self.timing = timing;
+        self
+    }
+}
+
+impl<HWCS: HwCs> TransferConfig<HWCS> {
+    pub fn new(
+        spi_clk: Hertz,
+        mode: Mode,
+        hw_cs: Option<HWCS>,
+        blockmode: bool,
+        sod: bool,
+    ) -> Self {
+        TransferConfig {
+            spi_clk,
+            mode,
+            hw_cs,
+            sod,
+            blockmode,
+            timing: TimingConfig::default(),
+        }
+    }
+
+    pub fn downgrade(self) -> ReducedTransferConfig {
+        ReducedTransferConfig {
+            spi_clk: self.spi_clk,
+            mode: self.mode,
+            sod: self.sod,
+            blockmode: self.blockmode,
+            hw_cs: HWCS::CS_ID,
+            timing: self.timing,
+        }
+    }
+}
+
+impl<HWCS: HwCs> GenericTransferConfig for TransferConfig<HWCS> {
+    /// Slave Output Disable
+    fn sod(&mut self, sod: bool) {
+        self.sod = sod;
+    }
+
+    fn blockmode(&mut self, blockmode: bool) {
+        self.blockmode = blockmode;
+    }
+
+    fn mode(&mut self, mode: Mode) {
+        self.mode = mode;
+    }
+
+    fn frequency(&mut self, spi_clk: Hertz) {
+        self.spi_clk = spi_clk;
+    }
+
+    fn hw_cs_id(&self) -> u8 {
+        HWCS::CS_ID as u8
+    }
+}
+
+#[derive(Default)]
+/// Configuration options for the whole SPI bus. See Programmer Guide p.92 for more details
+pub struct SpiConfig {
+    /// Serial clock rate divider. Together with the CLKPRESCALE register, it determines
+    /// the SPI clock rate in master mode. 0 by default. This value is only used if no transfer
+    /// configuration is passed to the constructor. Both dividers are calculated whenever the
+    /// SPI clock is configured
+    pub scrdv: u8,
+    /// Policy which is used if a requested SPI clock can not be generated exactly
+    pub clk_policy: ClockPolicy,
+    /// By default, configure SPI for master mode (ms == false)
+    ms: bool,
+    /// Slave output disable. Useful if separate GPIO pins or decoders are used for CS control
+    sod: bool,
+    /// Loopback mode. If you use this, don't connect MISO to MOSI, they will be tied internally
+    lbm: bool,
+    /// Enable Master Delayer Capture Mode. See Programmers Guide p.92 for more details. The
+    /// delayed capture can also be enabled for each device with the [`TimingConfig`] of the
+    /// transfer configuration
+    pub mdlycap: bool,
+}
+
+impl SpiConfig {
+    pub fn clock_policy(mut self, policy: ClockPolicy) -> Self {
+        self.clk_policy = policy;
+        self
+    }
+
+    pub fn loopback(mut self, enable: bool) -> Self {
+        self.lbm = enable;
+        self
+    }
+
+    pub fn master_mode(mut self, master: bool) -> Self {
+        self.ms = !master;
+        self
+    }
+
+    pub fn slave_output_disable(mut self, sod: bool) -> Self {
+        self.sod = sod;
+        self
+    }
+}
+
+//==================================================================================================
+// Word Size
+//==================================================================================================
+
+/// Configuration trait for the Word Size
+/// used by the SPI peripheral. It is implemented for [`u8`], [`u16`] and [`Frame`]
+pub trait Word: Copy {
+    fn word_reg() -> u8;
+    /// Convert the value read from the data register into a word
+    fn from_data(data: u32) -> Self;
+    /// Convert a word into the value written to the data register
+    fn to_data(self) -> u32;
+}
+
+impl Word for u8 {
+    fn word_reg() -> u8 {
+        0x07
+    }
+
+    fn from_data(data: u32) -> Self {
+        (data & 0xff) as u8
+    }
+
+    fn to_data(self) -> u32 {
+        self as u32
+    }
+}
+
+impl Word for u16 {
+    fn word_reg() -> u8 {
+        0x0f
+    }
+
+    fn from_data(data: u32) -> Self {
+        (data & 0xffff) as u16
+    }
+
+    fn to_data(self) -> u32 {
+        self as u32
+    }
+}
+
+/// Word with a size of `BITS` bits, which can be any size between 4 and 16 bits. Only the lower
+/// `BITS` bits of the value are transferred and the received data is masked accordingly.
+/// Using a size outside of the supported range leads to a compile time error
+///
+/// ## Example
+///
+/// A 12 bit DAC can be used with a `Spi<SPIA, PINS, Frame<12>>`, which is created with
+/// [`Spi::into_word_size`]
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub struct Frame<const BITS: u8>(u16);
+
+impl<const BITS: u8> Frame<BITS> {
+    /// Value of the word size field of the CTRL0 register
+    const SIZE_REG: u8 = {
+        assert!(
+            BITS >= 4 && BITS <= 16,
+            "SPI frames must have between 4 and 16 bits"
+        );
+        BITS - 1
+    };
+    pub const MASK: u16 = ((1_u32 << BITS) - 1) as u16;
+
+    /// Create a new frame. Bits above the frame size are cleared
+    #[inline]
+    pub const fn new(value: u16) -> Self {
+        Frame(value & Self::MASK)
+    }
+
+    #[inline]
+    pub const fn value(self) -> u16 {
+        self.0
+    }
+}
+
+impl<const BITS: u8> From<Frame<BITS>> for u16 {
+    fn from(frame: Frame<BITS>) -> Self {
+        frame.0
+    }
+}
+
+impl<const BITS: u8> Word for Frame<BITS> {
+    fn word_reg() -> u8 {
+        Self::SIZE_REG
+    }
+
+    fn from_data(data: u32) -> Self {
+        Frame::new(data as u16)
+    }
+
+    fn to_data(self) -> u32 {
+        self.0 as u32
+    }
+}
+
+//==================================================================================================
+// Spi
+//==================================================================================================
+
+pub struct SpiBase<SPI, Word = u8> {
+    spi: SPI,
+    cfg: SpiConfig,
+    sys_clk: Hertz,
+    /// SPI clock which is achieved with the current divider configuration
+    spi_clk: Hertz,
+    blockmode: bool,
+    /// Timing of the currently applied transfer configuration
+    timing: TimingConfig,
+    /// Word which is written once the write buffer of a transfer is exhausted
+    fill_word: Word,
+    _word: PhantomData<Word>,
+}
+pub struct Spi<SPI, PINS, Word = u8> {
+    spi_base: SpiBase<SPI, Word>,
+    pins: PINS,
+}
+
+// Re-export this so it can be used for the constructor
+pub use crate::typelevel::NoneT;
+
+pub trait Instance: Deref<Target = spi_base::RegisterBlock> {
+    /// Index of the SPI peripheral, used for the IRQSEL interrupt redirection
+    const IDX: u8;
+
+    fn ptr() -> *const spi_base::RegisterBlock;
+}
+
+macro_rules! spi_instance {
+    ($($SPIX:ident: $idx:expr,)+) => {
+        $(
+            impl Instance for $SPIX {
+                const IDX: u8 = $idx;
+
+                fn ptr() -> *const spi_base::RegisterBlock {
+                    $SPIX::ptr() as *const _
+                }
+            }
+        )+
+    }
+}
+
+spi_instance!(
+    SPIA: 0,
+    SPIB: 1,
+    SPIC: 2,
+);
+
+/// Route the interrupt of the SPI peripheral to the given NVIC interrupt using IRQSEL. This also
+/// activates the peripheral clock for IRQSEL
+pub(crate) fn route_irq<SPI: Instance>(
+    syscfg: &mut SYSCONFIG,
+    irqsel: &mut IRQSEL,
+    interrupt: Interrupt,
+) {
+    enable_peripheral_clock(syscfg, PeripheralClocks::Irqsel);
+    irqsel.spi[SPI::IDX as usize].write(|w| unsafe { w.bits(interrupt as u32) });
+}
+
+/// Remove the interrupt routing of the SPI peripheral
+pub(crate) fn unroute_irq<SPI: Instance>(irqsel: &mut IRQSEL) {
+    irqsel.spi[SPI::IDX as usize].write(|w| unsafe { w.bits(IRQ_DST_NONE) });
+}
+
+impl<SPI: Instance, WORD: Word> SpiBase<SPI, WORD> {
+    /// Configure the SPI clock. The dividers are selected with the clock policy of the
+    /// [`SpiConfig`]. Returns the achieved SPI clock. The configuration is not changed if the
+    /// SPI clock can not be generated
+    pub fn cfg_clock(&mut self, spi_clk: Hertz) -> Result<Hertz, ClockError> {
+        let divisor = calculate_clock_divisor(self.sys_clk, spi_clk, self.cfg.clk_policy)?;
+        self.spi
+            .ctrl0
+            .modify(|_, w| unsafe { w.scrdv().bits(divisor.scrdv) });
+        self.spi
+            .clkprescale
+            .write(|w| unsafe { w.bits(divisor.clk_prescale as u32) });
+        self.cfg.scrdv = divisor.scrdv;
+        self.spi_clk = divisor.achieved_clk;
+        Ok(divisor.achieved_clk)
+    }
+
+    /// SPI clock which is achieved with the current configuration
+    #[inline]
+    pub fn spi_clk(&self) -> Hertz {
+        self.spi_clk
+    }
+
+    #[inline]
+    pub fn cfg_mode(&mut self, mode: Mode) {
+        let (cpo_bit, cph_bit) = match mode {
+            MODE_0 => (false, false),
+            MODE_1 => (false, true),
+            MODE_2 => (true, false),
+            MODE_3 => (true, true),
+        };
+        self.spi.ctrl0.modify(|_, w| {
+            w.spo().bit(cpo_bit);
+            w.sph().bit(cph_bit)
+        });
+    }
+
+    #[inline]
+    pub fn perid(&self) -> u32 {
+        self.spi.perid.read().bits()
+    }
+
+    /// Apply a transfer configuration. Returns the achieved SPI clock. The configuration is not
+    /// changed if the SPI clock can not be generated
+    pub fn cfg_transfer<HwCs: OptionalHwCs<SPI>>(
+        &mut self,
+        transfer_cfg: &TransferConfig<HwCs>,
+    ) -> Result<Hertz, ClockError> {
+        let spi_clk = self.cfg_clock(transfer_cfg.spi_clk)?;
+        self.cfg_mode(transfer_cfg.mode);
+        self.blockmode = transfer_cfg.blockmode;
+        self.timing = transfer_cfg.timing;
+        self.spi.ctrl1.modify(|_, w| {
+            if transfer_cfg.sod {
+                w.sod().set_bit();
+            } else if transfer_cfg.hw_cs.is_some() {
+                w.sod().clear_bit();
+                unsafe {
+                    w.ss().bits(HwCs::CS_ID as u8);
+                }
+            } else {
+                w.sod().clear_bit();
+            }
+            if transfer_cfg.blockmode {
+                w.blockmode().set_bit();
+            } else {
+                w.blockmode().clear_bit();
+            }
+            w.mdlycap()
+                .bit(self.cfg.mdlycap || transfer_cfg.timing.delayed_capture)
+        });
+        Ok(spi_clk)
+    }
+
+    /// Route the SPI interrupt to the given NVIC interrupt using the IRQSEL peripheral.
+    /// This also activates the peripheral clock for IRQSEL.
+    ///
+    /// The NVIC interrupt is not unmasked, this needs to be done by the caller
+    pub fn route_irq(&mut self, syscfg: &mut SYSCONFIG, irqsel: &mut IRQSEL, interrupt: Interrupt) {
+        route_irq::<SPI>(syscfg, irqsel, interrupt);
+    }
+
+    /// Remove the interrupt routing of the SPI peripheral
+    pub fn unroute_irq(&mut self, irqsel: &mut IRQSEL) {
+        unroute_irq::<SPI>(irqsel);
+    }
+}
+
+impl<SPI: Instance, WORD: Word> SpiBase<SPI, WORD> {
+    /// Configure the peripheral and enable it. The peripheral clock is enabled if the system
+    /// configuration peripheral is passed together with the peripheral clock of the SPI
+    fn new(
+        spi: SPI,
+        sys_clk: Hertz,
+        mut spi_cfg: SpiConfig,
+        syscfg: Option<(&mut SYSCONFIG, PeripheralClocks)>,
+        transfer_cfg: Option<&ReducedTransferConfig>,
+    ) -> Result<Self, ClockError> {
+        let mut divisor = ClockDivisor::from_regs(sys_clk, MIN_CLK_PRESCALE, spi_cfg.scrdv);
+        if let Some(transfer_cfg) = transfer_cfg {
+            divisor = calculate_clock_divisor(sys_clk, transfer_cfg.spi_clk, spi_cfg.clk_policy)?;
+            spi_cfg.scrdv = divisor.scrdv;
+        }
+        if let Some((syscfg, clk_enb)) = syscfg {
+            enable_peripheral_clock(syscfg, clk_enb);
+        }
+        let SpiConfig {
+            scrdv,
+            ms,
+            sod,
+            lbm,
+            mdlycap,
+            ..
+        } = spi_cfg;
+        let mut mode = MODE_0;
+        let mut ss = 0;
+        let mut init_blockmode = false;
+        let mut timing = TimingConfig::default();
+        if let Some(transfer_cfg) = transfer_cfg {
+            mode = transfer_cfg.mode;
+            if transfer_cfg.hw_cs != HwChipSelectId::Invalid {
+                ss = transfer_cfg.hw_cs as u8;
+            }
+            init_blockmode = transfer_cfg.blockmode;
+            timing = transfer_cfg.timing;
+        }
+
+        let (cpo_bit, cph_bit) = match mode {
+            MODE_0 => (false, false),
+            MODE_1 => (false, true),
+            MODE_2 => (true, false),
+            MODE_3 => (true, true),
+        };
+        spi.ctrl0.write(|w| {
+            unsafe {
+                w.size().bits(WORD::word_reg());
+                w.scrdv().bits(scrdv);
+                // Clear clock phase and polarity. Will be set to correct value for each
+                // transfer
+                w.spo().bit(cpo_bit);
+                w.sph().bit(cph_bit)
+            }
+        });
+        spi.ctrl1.write(|w| {
+            w.lbm().bit(lbm);
+            w.sod().bit(sod);
+            w.ms().bit(ms);
+            w.mdlycap().bit(mdlycap || timing.delayed_capture);
+            unsafe { w.ss().bits(ss) }
+        });
+
+        spi.fifo_clr.write(|w| {
+            w.rxfifo().set_bit();
+            w.txfifo().set_bit()
+        });
+        spi.clkprescale
+            .write(|w| unsafe { w.bits(divisor.clk_prescale as u32) });
+        // Enable the peripheral as the last step as recommended in the
+        // programmers guide
+        spi.ctrl1.modify(|_, w| w.enable().set_bit());
+        Ok(SpiBase {
+            spi,
+            cfg: spi_cfg,
+            sys_clk,
+            spi_clk: divisor.achieved_clk,
+            blockmode: init_blockmode,
+            timing,
+            fill_word: WORD::from_data(0),
+            _word: PhantomData,
+        })
+    }
+}
+
+impl<WORD: Word> SpiBase<SPIC, WORD> {
+    /// Create a SPI driver for the boot memory interface of SPIC. The boot memory is connected
+    /// with dedicated ROM pins, so no pins need to be passed. The boot memory is selected with
+    /// the [`RomCs`] chip select.
+    ///
+    /// The arguments are the same as for the [`Spi`] constructors
+    pub fn spic_rom(
+        spi: SPIC,
+        sys_clk: impl Into<Hertz>,
+        spi_cfg: SpiConfig,
+        syscfg: Option<&mut SYSCONFIG>,
+        transfer_cfg: Option<&ReducedTransferConfig>,
+    ) -> Result<Self, ClockError> {
+        SpiBase::new(
+            spi,
+            sys_clk.into(),
+            spi_cfg,
+            syscfg.map(|syscfg| (syscfg, PeripheralClocks::Spi2)),
+            transfer_cfg,
+        )
+    }
+}
+
+macro_rules! spi {
+    ($($SPIX:ident: ($spix:ident, $clk_enb:path),)+) => {
+        $(
+            impl<Sck: PinSck<$SPIX>, Miso: PinMiso<$SPIX>, Mosi: PinMosi<$SPIX>,
+                WORD: Word> Spi<$SPIX, (Sck, Miso, Mosi), WORD>
+            {
+                /// Create a new SPI struct
+                ///
+                /// You can delete the pin type information by calling the
+                /// [`downgrade`](Self::downgrade) function
+                ///
+                /// ## Arguments
+                /// * `spi` - SPI bus to use
+                /// * `pins` - Pins to be used for SPI transactions. These pins are consumed
+                ///     to ensure the pins can not be used for other purposes anymore
+                /// * `spi_cfg` - Configuration specific to the SPI bus
+                /// * `transfer_cfg` - Optional initial transfer configuration which includes
+                ///     configuration which can change across individual SPI transfers like SPI mode
+                ///     or SPI clock. If only one device is connected, this configuration only needs
+                ///     to be done once.
+                /// * `syscfg` - Can be passed optionally to enable the peripheral clock
+                ///
+                /// Returns [`ClockError::Unreachable`] if the SPI clock of the transfer
+                /// configuration can not be generated. The achieved SPI clock can be retrieved
+                /// with [`spi_clk`](Self::spi_clk)
+                pub fn $spix(
+                    spi: $SPIX,
+                    pins: (Sck, Miso, Mosi),
+                    sys_clk: impl Into<Hertz> + Copy,
+                    spi_cfg: SpiConfig,
+                    syscfg: Option<&mut SYSCONFIG>,
+                    transfer_cfg: Option<&ReducedTransferConfig>,
+                ) -> Result<Self, ClockError> {
+                    Ok(Spi {
+                        spi_base: SpiBase::new(
+                            spi,
+                            sys_clk.into(),
+                            spi_cfg,
+                            syscfg.map(|syscfg| (syscfg, $clk_enb)),
+                            transfer_cfg,
+                        )?,
+                        pins,
+                    })
+                }
+
+                #[inline]
+                pub fn cfg_clock(&mut self, spi_clk: Hertz) -> Result<Hertz, ClockError> {
+                    self.spi_base.cfg_clock(spi_clk)
+                }
+
+                #[inline]
+                pub fn spi_clk(&self) -> Hertz {
+                    self.spi_base.spi_clk()
+                }
+
+                #[inline]
+                pub fn cfg_mode(&mut self, mode: Mode) {
+                    self.spi_base.cfg_mode(mode);
+                }
+
+                #[inline]
+                pub fn perid(&self) -> u32 {
+                    self.spi_base.perid()
+                }
+
+                pub fn cfg_transfer<HwCs: OptionalHwCs<$SPIX>>(
+                    &mut self,
+                    transfer_cfg: &TransferConfig<HwCs>,
+                ) -> Result<Hertz, ClockError> {
+                    self.spi_base.cfg_transfer(transfer_cfg)
+                }
+
+                #[inline]
+                pub fn set_fill_word(&mut self, fill_word: WORD) {
+                    self.spi_base.set_fill_word(fill_word);
+                }
+
+                #[inline]
+                pub fn fill_word(&self) -> WORD {
+                    self.spi_base.fill_word()
+                }
+
+                #[inline]
+                pub fn transfer_buffers(&mut self, read: &mut [WORD], write: &[WORD]) {
+                    self.spi_base.transfer_buffers(read, write);
+                }
+
+                #[inline]
+                pub fn write_then_read(&mut self, write: &[WORD], read: &mut [WORD]) {
+                    self.spi_base.write_then_read(write, read);
+                }
+
+                #[inline]
+                pub fn route_irq(
+                    &mut self,
+                    syscfg: &mut SYSCONFIG,
+                    irqsel: &mut IRQSEL,
+                    interrupt: Interrupt,
+                ) {
+                    self.spi_base.route_irq(syscfg, irqsel, interrupt);
+                }
+
+                #[inline]
+                pub fn unroute_irq(&mut self, irqsel: &mut IRQSEL) {
+                    self.spi_base.unroute_irq(irqsel);
+                }
+
+                /// Releases the SPI peripheral and associated pins
+                pub fn release(self) -> ($SPIX, (Sck, Miso, Mosi), SpiConfig) {
+                    (self.spi_base.spi, self.pins, self.spi_base.cfg)
+                }
+
+                pub fn downgrade(self) -> SpiBase<$SPIX, WORD> {
+                    self.spi_base
+                }
+            }
+        )+
+    }
+}
+
+spi!(
+    SPIA: (spia, PeripheralClocks::Spi0),
+    SPIB: (spib, PeripheralClocks::Spi1),
+    SPIC: (spic, PeripheralClocks::Spi2),
+);
+
+//==================================================================================================
+// Word size conversion
+//==================================================================================================
+
+impl<SPI: Instance, WORD: Word> SpiBase<SPI, WORD> {
+    /// Change the word size of the SPI peripheral
+    pub fn into_word_size<NEW: Word>(self) -> SpiBase<SPI, NEW> {
+        self.spi
+            .ctrl0
+            .modify(|_, w| unsafe { w.size().bits(NEW::word_reg()) });
+        SpiBase {
+            spi: self.spi,
+            cfg: self.cfg,
+            sys_clk: self.sys_clk,
+            spi_clk: self.spi_clk,
+            blockmode: self.blockmode,
+            timing: self.timing,
+            fill_word: NEW::from_data(self.fill_word.to_data()),
+            _word: PhantomData,
+        }
+    }
+}
+
+impl<SPI: Instance, PINS, WORD: Word> Spi<SPI, PINS, WORD> {
+    /// Change the word size of the SPI peripheral
+    pub fn into_word_size<NEW: Word>(self) -> Spi<SPI, PINS, NEW> {
+        Spi {
+            spi_base: self.spi_base.into_word_size(),
+            pins: self.pins,
+        }
+    }
+}
+
+/// Changing the word size also requires a type conversion
+impl<SPI: Instance, PINS> From<Spi<SPI, PINS, u8>> for Spi<SPI, PINS, u16> {
+    fn from(old_spi: Spi<SPI, PINS, u8>) -> Self {
+        old_spi.into_word_size()
+    }
+}
+
+/// Changing the word size also requires a type conversion
+impl<SPI: Instance, PINS> From<Spi<SPI, PINS, u16>> for Spi<SPI, PINS, u8> {
+    fn from(old_spi: Spi<SPI, PINS, u16>) -> Self {
+        old_spi.into_word_size()
+    }
+}
+
+//==================================================================================================
+// embedded-hal 0.2 traits
+//==================================================================================================
+
+impl<SPI: Instance, WORD: Word> FullDuplex<WORD> for SpiBase<SPI, WORD> {
+    type Error = Infallible;
+
+    /// Sends a word to the slave
+    #[inline(always)]
+    fn send(&mut self, word: WORD) -> nb::Result<(), Self::Error> {
+        if self.spi.status.read().tnf().bit_is_clear() {
+            return Err(nb::Error::WouldBlock);
+        }
+        self.spi.data.write(|w| unsafe { w.bits(word.to_data()) });
+        Ok(())
+    }
+
+    /// Read a word from the slave. Must be preceeded by a [`send`](Self::send) call
+    #[inline(always)]
+    fn read(&mut self) -> nb::Result<WORD, Self::Error> {
+        if self.spi.status.read().rne().bit_is_clear() {
+            return Err(nb::Error::WouldBlock);
+        }
+        Ok(WORD::from_data(self.spi.data.read().bits()))
+    }
+}
+
+impl<SPI: Instance, PINS, WORD: Word> FullDuplex<WORD> for Spi<SPI, PINS, WORD> {
+    type Error = Infallible;
+
+    #[inline(always)]
+    fn read(&mut self) -> nb::Result<WORD, Self::Error> {
+        self.spi_base.read()
+    }
+
+    #[inline(always)]
+    fn send(&mut self, word: WORD) -> nb::Result<(), Self::Error> {
+        self.spi_base.send(word)
+    }
+}
+
+// The blocking traits are implemented for each word type separately. Generic implementations
+// would conflict with the default implementations of embedded-hal
+macro_rules! spi_blocking {
+    ($([$($gen:tt)*] $WORD:ty),+) => {
+        $(
+            impl<SPI: Instance, $($gen)*> blocking::spi::Transfer<$WORD>
+                for SpiBase<SPI, $WORD>
+            {
+                type Error = Infallible;
+
+                fn transfer<'w>(
+                    &mut self,
+                    words: &'w mut [$WORD],
+                ) -> Result<&'w [$WORD], Self::Error> {
+                    self.in_block_frame(|spi| spi.transfer_words(InPlaceBuffer(words)));
+                    Ok(words)
+                }
+            }
+
+            impl<SPI: Instance, PINS, $($gen)*> blocking::spi::Transfer<$WORD>
+                for Spi<SPI, PINS, $WORD>
+            {
+                type Error = Infallible;
+
+                fn transfer<'w>(
+                    &mut self,
+                    words: &'w mut [$WORD],
+                ) -> Result<&'w [$WORD], Self::Error> {
+                    self.spi_base.transfer(words)
+                }
+            }
+
+            impl<SPI: Instance, $($gen)*> blocking::spi::Write<$WORD> for SpiBase<SPI, $WORD> {
+                type Error = Infallible;
+                fn write(&mut self, words: &[$WORD]) -> Result<(), Self::Error> {
+                    self.transfer_buffers(&mut [], words);
+                    Ok(())
+                }
+            }
+
+            impl<SPI: Instance, PINS, $($gen)*> blocking::spi::Write<$WORD>
+                for Spi<SPI, PINS, $WORD>
+            {
+                type Error = Infallible;
+                fn write(&mut self, words: &[$WORD]) -> Result<(), Self::Error> {
+                    self.spi_base.write(words)
+                }
+            }
+        )+
+    };
+}
+
+spi_blocking!([] u8, [] u16, [const BITS: u8] Frame<BITS>);
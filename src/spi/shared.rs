@@ -0,0 +1,285 @@
+//! # Shared SPI bus
+//!
+//! A [`SpiBase`] can be shared between several device drivers by placing it inside a
+//! [`RefCellSpiBus`], which can be used from a single execution context, or inside a
+//! [`CriticalSectionSpiBus`], which can also be shared with interrupt handlers.
+//!
+//! Every driver receives its own [`SharedSpiDevice`] handle which implements the
+//! embedded-hal 1.0 [`SpiDevice`](spi::SpiDevice) trait. A handle carries the
+//! [`TransferConfig`] of its device and uses either a hardware chip select of the peripheral or
+//! a GPIO [`OutputPin`] as a software chip select, wrapped in a [`GpioCs`]. The transfer
+//! configuration is only applied to the peripheral when the bus was used by another device
+//! before.
+//!
+//! Hardware chip selects are kept asserted for the full transaction, in the same way as with
+//! the [`SpiDevice`](super::SpiDevice). Software chip selects are active low. The chip select
+//! setup and hold times of the [`TimingConfig`](super::TimingConfig) are supported for software
+//! chip selects.
+use core::cell::RefCell;
+use core::convert::Infallible;
+use core::fmt::Debug;
+
+use cortex_m::interrupt::Mutex;
+use embedded_hal::digital::v2::OutputPin;
+use embedded_hal_1::spi::{self, ErrorKind, ErrorType, Operation};
+
+use super::{
+    hal_1::run_operations, ClockError, Instance, OptionalHwCs, SpiBase, TransferConfig, Word,
+};
+use crate::{typelevel::NoneT, Sealed};
+
+/// Bus state which is protected by the shared bus implementations
+pub struct SharedBusState<SPI, WORD> {
+    spi: SpiBase<SPI, WORD>,
+    /// Device which performed the last transaction. The peripheral is configured for
+    /// this device
+    active: Option<u8>,
+    next_id: u8,
+}
+
+impl<SPI, WORD> SharedBusState<SPI, WORD> {
+    fn new(spi: SpiBase<SPI, WORD>) -> Self {
+        SharedBusState {
+            spi,
+            active: None,
+            next_id: 0,
+        }
+    }
+
+    fn allocate_id(&mut self) -> u8 {
+        let id = self.next_id;
+        self.next_id = self
+            .next_id
+            .checked_add(1)
+            .expect("too many SPI bus devices");
+        id
+    }
+}
+
+/// Common interface of the shared bus implementations
+pub trait SharedBus: Sealed {
+    type Spi: Instance;
+    type Word: Word;
+
+    /// Get exclusive access to the bus state for the duration of the closure
+    fn lock<R>(&self, f: impl FnOnce(&mut SharedBusState<Self::Spi, Self::Word>) -> R) -> R;
+}
+
+/// SPI bus which can be shared between devices inside a single execution context
+pub struct RefCellSpiBus<SPI, WORD = u8> {
+    state: RefCell<SharedBusState<SPI, WORD>>,
+}
+
+impl<SPI: Instance, WORD: Word> RefCellSpiBus<SPI, WORD> {
+    pub fn new(spi: SpiBase<SPI, WORD>) -> Self {
+        RefCellSpiBus {
+            state: RefCell::new(SharedBusState::new(spi)),
+        }
+    }
+
+    pub fn release(self) -> SpiBase<SPI, WORD> {
+        self.state.into_inner().spi
+    }
+}
+
+impl<SPI, WORD> Sealed for RefCellSpiBus<SPI, WORD> {}
+
+impl<SPI: Instance, WORD: Word> SharedBus for RefCellSpiBus<SPI, WORD> {
+    type Spi = SPI;
+    type Word = WORD;
+
+    /// Panics if the bus is already in use, for example if a transaction is started from
+    /// inside another transaction
+    fn lock<R>(&self, f: impl FnOnce(&mut SharedBusState<SPI, WORD>) -> R) -> R {
+        f(&mut self.state.borrow_mut())
+    }
+}
+
+/// SPI bus which can be shared between devices in different execution contexts. The bus is
+/// locked with a critical section, so interrupts are disabled for the full duration of a
+/// transaction
+pub struct CriticalSectionSpiBus<SPI, WORD = u8> {
+    // The state is only taken out when the bus is released. The mutex does not provide a way to
+    // move the value out
+    state: Mutex<RefCell<Option<SharedBusState<SPI, WORD>>>>,
+}
+
+impl<SPI: Instance, WORD: Word> CriticalSectionSpiBus<SPI, WORD> {
+    pub fn new(spi: SpiBase<SPI, WORD>) -> Self {
+        CriticalSectionSpiBus {
+            state: Mutex::new(RefCell::new(Some(SharedBusState::new(spi)))),
+        }
+    }
+
+    pub fn release(self) -> SpiBase<SPI, WORD> {
+        let state = cortex_m::interrupt::free(|cs| self.state.borrow(cs).take());
+        state.unwrap().spi
+    }
+}
+
+impl<SPI, WORD> Sealed for CriticalSectionSpiBus<SPI, WORD> {}
+
+impl<SPI: Instance, WORD: Word> SharedBus for CriticalSectionSpiBus<SPI, WORD> {
+    type Spi = SPI;
+    type Word = WORD;
+
+    fn lock<R>(&self, f: impl FnOnce(&mut SharedBusState<SPI, WORD>) -> R) -> R {
+        cortex_m::interrupt::free(|cs| {
+            let mut state = self.state.borrow(cs).borrow_mut();
+            f(state.as_mut().unwrap())
+        })
+    }
+}
+
+/// Software chip select of a device
+pub trait DeviceCs {
+    type Error: Debug;
+
+    fn select(&mut self) -> Result<(), Self::Error>;
+    fn deselect(&mut self) -> Result<(), Self::Error>;
+}
+
+/// No software chip select. This is used for devices which use a hardware chip select
+impl DeviceCs for NoneT {
+    type Error = Infallible;
+
+    fn select(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn deselect(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Active low software chip select using a GPIO output pin
+pub struct GpioCs<PIN>(pub PIN);
+
+impl<PIN: OutputPin> DeviceCs for GpioCs<PIN>
+where
+    PIN::Error: Debug,
+{
+    type Error = PIN::Error;
+
+    fn select(&mut self) -> Result<(), Self::Error> {
+        self.0.set_low()
+    }
+
+    fn deselect(&mut self) -> Result<(), Self::Error> {
+        self.0.set_high()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum SharedSpiError<E> {
+    /// Setting the software chip select failed
+    ChipSelect(E),
+    /// The SPI clock of the transfer configuration can not be generated
+    Clock(ClockError),
+}
+
+impl<E: Debug> spi::Error for SharedSpiError<E> {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            SharedSpiError::ChipSelect(_) => ErrorKind::ChipSelectFault,
+            SharedSpiError::Clock(_) => ErrorKind::Other,
+        }
+    }
+}
+
+/// Handle of a single device on a shared SPI bus
+pub struct SharedSpiDevice<'a, BUS, HWCS, CS = NoneT> {
+    bus: &'a BUS,
+    id: u8,
+    transfer_cfg: TransferConfig<HWCS>,
+    cs: CS,
+}
+
+impl<'a, BUS: SharedBus, HWCS: OptionalHwCs<BUS::Spi>> SharedSpiDevice<'a, BUS, HWCS, NoneT> {
+    /// Create a device handle which uses the hardware chip select of the transfer
+    /// configuration, if there is one
+    pub fn new(bus: &'a BUS, transfer_cfg: TransferConfig<HWCS>) -> Self {
+        SharedSpiDevice {
+            bus,
+            id: bus.lock(|state| state.allocate_id()),
+            transfer_cfg,
+            cs: NoneT,
+        }
+    }
+}
+
+impl<'a, BUS: SharedBus, PIN: OutputPin> SharedSpiDevice<'a, BUS, NoneT, GpioCs<PIN>>
+where
+    PIN::Error: Debug,
+{
+    /// Create a device handle which uses a GPIO pin as the chip select. The pin is driven high
+    /// initially. The hardware chip select outputs are disabled for transactions of this
+    /// device by setting the SOD bit of the transfer configuration
+    pub fn new_with_gpio_cs(
+        bus: &'a BUS,
+        mut transfer_cfg: TransferConfig<NoneT>,
+        cs_pin: PIN,
+    ) -> Result<Self, PIN::Error> {
+        let mut cs = GpioCs(cs_pin);
+        cs.deselect()?;
+        transfer_cfg.sod = true;
+        Ok(SharedSpiDevice {
+            bus,
+            id: bus.lock(|state| state.allocate_id()),
+            transfer_cfg,
+            cs,
+        })
+    }
+}
+
+impl<BUS, HWCS, CS> SharedSpiDevice<'_, BUS, HWCS, CS> {
+    #[inline]
+    pub fn transfer_cfg(&self) -> &TransferConfig<HWCS> {
+        &self.transfer_cfg
+    }
+
+    pub fn release(self) -> (TransferConfig<HWCS>, CS) {
+        (self.transfer_cfg, self.cs)
+    }
+}
+
+impl<BUS, HWCS, CS: DeviceCs> ErrorType for SharedSpiDevice<'_, BUS, HWCS, CS> {
+    type Error = SharedSpiError<CS::Error>;
+}
+
+impl<BUS, HWCS, CS> spi::SpiDevice<BUS::Word> for SharedSpiDevice<'_, BUS, HWCS, CS>
+where
+    BUS: SharedBus,
+    BUS::Word: 'static,
+    HWCS: OptionalHwCs<BUS::Spi>,
+    CS: DeviceCs,
+{
+    fn transaction(
+        &mut self,
+        operations: &mut [Operation<'_, BUS::Word>],
+    ) -> Result<(), Self::Error> {
+        let id = self.id;
+        let transfer_cfg = &self.transfer_cfg;
+        let cs = &mut self.cs;
+        self.bus.lock(|state| {
+            if state.active != Some(id) {
+                state
+                    .spi
+                    .cfg_transfer(transfer_cfg)
+                    .map_err(SharedSpiError::Clock)?;
+                state.active = Some(id);
+            }
+            // The timing of hardware chip selects is handled when the operations are performed
+            let sw_cs = transfer_cfg.hw_cs.is_none();
+            cs.select().map_err(SharedSpiError::ChipSelect)?;
+            if sw_cs {
+                state.spi.delay_ns(transfer_cfg.timing.cs_setup_ns);
+            }
+            run_operations(&mut state.spi, transfer_cfg, operations);
+            if sw_cs {
+                state.spi.delay_ns(transfer_cfg.timing.cs_hold_ns);
+            }
+            cs.deselect().map_err(SharedSpiError::ChipSelect)
+        })
+    }
+}
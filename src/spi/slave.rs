@@ -0,0 +1,422 @@
+//! # SPI slave mode
+//!
+//! The [`SpiSlave`] configures a SPI peripheral as a slave device which is clocked by an
+//! external master. In slave mode, the SCK, MOSI and SSn pins are inputs and the MISO pin is an
+//! output. The slave select input is always the SSn0 pin of the peripheral, so only SPIA and
+//! SPIB can be used in slave mode.
+//!
+//! Because the master drives the clock, the slave needs to place the reply data into the TX FIFO
+//! before the master starts clocking with [`preload`](SpiSlave::preload). If the TX FIFO
+//! runs empty during a transfer, the slave shifts out zeros.
+//!
+//! The slave can be used in a blocking way with [`read_timeout`](SpiSlave::read_timeout) or
+//! interrupt-driven with [`start_transfer`](SpiSlave::start_transfer) and
+//! [`on_interrupt`](SpiSlave::on_interrupt).
+use core::marker::PhantomData;
+
+use embedded_hal::{spi::Mode, timer::CountDown};
+
+use crate::{
+    clock::{enable_peripheral_clock, PeripheralClocks},
+    gpio::pins::{AltFunc1, AltFunc2, Pin, PA17, PA28, PB12, PB16, PB2, PB6},
+    pac::{Interrupt, IRQSEL, SPIA, SPIB, SYSCONFIG},
+    Sealed,
+};
+
+use super::{
+    route_irq, unroute_irq, Instance, PinMiso, PinMosi, PinSck, Word, FIFO_DEPTH, MODE_0, MODE_1,
+    MODE_2, MODE_3,
+};
+
+//==================================================================================================
+// Pins
+//==================================================================================================
+
+/// Slave select input pin. This is always the SSn0 pin of the peripheral
+pub trait PinSs<SPI>: Sealed {}
+
+impl PinSs<SPIA> for Pin<PA28, AltFunc1> {}
+impl PinSs<SPIA> for Pin<PB6, AltFunc2> {}
+
+impl PinSs<SPIB> for Pin<PB16, AltFunc1> {}
+impl PinSs<SPIB> for Pin<PB2, AltFunc1> {}
+impl PinSs<SPIB> for Pin<PB12, AltFunc2> {}
+impl PinSs<SPIB> for Pin<PA17, AltFunc2> {}
+
+//==================================================================================================
+// Definitions
+//==================================================================================================
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum SlaveEvent {
+    /// A word was received while the RX FIFO was full
+    RxOverrun,
+    /// The RX FIFO is not empty and no word was received for some time
+    RxTimeout,
+    /// The RX FIFO is at least half full
+    RxFifoHalfFull,
+    /// The TX FIFO is at least half empty
+    TxFifoHalfEmpty,
+}
+
+/// Edges of the slave select signal, detected with [`SpiSlave::cs_event`]
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum CsEvent {
+    /// The master asserted the slave select signal and started a new frame. The next word in
+    /// the RX FIFO is the first word of the frame
+    Asserted,
+    /// The frame was completed
+    Deasserted,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum SlaveError {
+    /// Not all words were received within the timeout
+    Timeout { received: usize },
+    /// Received data was lost because the RX FIFO was full
+    Overrun,
+}
+
+struct IrqTransfer<WORD: 'static> {
+    tx: &'static [WORD],
+    tx_idx: usize,
+    rx: &'static mut [WORD],
+    rx_idx: usize,
+    overrun: bool,
+    completed: bool,
+}
+
+//==================================================================================================
+// SPI slave
+//==================================================================================================
+
+/// SPI peripheral in slave mode
+pub struct SpiSlave<SPI, PINS, WORD: 'static = u8> {
+    spi: SPI,
+    pins: PINS,
+    in_frame: bool,
+    start_reported: bool,
+    transfer: Option<IrqTransfer<WORD>>,
+    _word: PhantomData<WORD>,
+}
+
+macro_rules! spi_slave {
+    ($($SPIX:ident: ($spix:ident, $clk_enb:path),)+) => {
+        $(
+            impl<Sck: PinSck<$SPIX>, Miso: PinMiso<$SPIX>, Mosi: PinMosi<$SPIX>,
+                Ss: PinSs<$SPIX>, WORD: Word> SpiSlave<$SPIX, (Sck, Miso, Mosi, Ss), WORD>
+            {
+                /// Create a new SPI slave
+                ///
+                /// ## Arguments
+                /// * `spi` - SPI bus to use
+                /// * `pins` - SCK, MISO, MOSI and SSn pins. These pins are consumed
+                ///   to ensure the pins can not be used for other purposes anymore
+                /// * `mode` - SPI mode which is used by the master
+                /// * `syscfg` - Can be passed optionally to enable the peripheral clock
+                pub fn $spix(
+                    spi: $SPIX,
+                    pins: (Sck, Miso, Mosi, Ss),
+                    mode: Mode,
+                    syscfg: Option<&mut SYSCONFIG>,
+                ) -> Self {
+                    if let Some(syscfg) = syscfg {
+                        enable_peripheral_clock(syscfg, $clk_enb);
+                    }
+                    let (cpo_bit, cph_bit) = match mode {
+                        MODE_0 => (false, false),
+                        MODE_1 => (false, true),
+                        MODE_2 => (true, false),
+                        MODE_3 => (true, true),
+                    };
+                    spi.ctrl0.write(|w| {
+                        unsafe {
+                            w.size().bits(WORD::word_reg());
+                            w.spo().bit(cpo_bit);
+                            w.sph().bit(cph_bit)
+                        }
+                    });
+                    // The block mode start status marks the first word received after the
+                    // slave select signal was asserted
+                    spi.ctrl1.write(|w| {
+                        w.ms().set_bit();
+                        w.blockmode().set_bit();
+                        w.bmstart().set_bit()
+                    });
+                    spi.fifo_clr.write(|w| {
+                        w.rxfifo().set_bit();
+                        w.txfifo().set_bit()
+                    });
+                    spi.irq_enb.reset();
+                    spi.ctrl1.modify(|_, w| w.enable().set_bit());
+                    SpiSlave {
+                        spi,
+                        pins,
+                        in_frame: false,
+                        start_reported: false,
+                        transfer: None,
+                        _word: PhantomData,
+                    }
+                }
+
+                /// Disable the peripheral and release the SPI peripheral and the pins
+                pub fn release(self) -> ($SPIX, (Sck, Miso, Mosi, Ss)) {
+                    self.spi.irq_enb.reset();
+                    self.spi.ctrl1.reset();
+                    (self.spi, self.pins)
+                }
+            }
+        )+
+    }
+}
+
+spi_slave!(
+    SPIA: (spia, PeripheralClocks::Spi0),
+    SPIB: (spib, PeripheralClocks::Spi1),
+);
+
+impl<SPI: Instance, PINS, WORD: Word> SpiSlave<SPI, PINS, WORD> {
+    /// Write as many words as possible into the TX FIFO and return the number of written words.
+    /// These words are shifted out when the master starts clocking
+    pub fn preload(&mut self, words: &[WORD]) -> usize {
+        let mut written = 0;
+        for word in words {
+            if self.write(*word).is_err() {
+                break;
+            }
+            written += 1;
+        }
+        written
+    }
+
+    /// Write a single word into the TX FIFO
+    #[inline]
+    pub fn write(&mut self, word: WORD) -> nb::Result<(), core::convert::Infallible> {
+        if self.spi.status.read().tnf().bit_is_clear() {
+            return Err(nb::Error::WouldBlock);
+        }
+        self.spi.data.write(|w| unsafe { w.bits(word.to_data()) });
+        Ok(())
+    }
+
+    /// Read a single word from the RX FIFO
+    pub fn read(&mut self) -> nb::Result<WORD, SlaveError> {
+        if self.spi.irq_raw.read().rorim().bit_is_set() {
+            self.spi.irq_clr.write(|w| w.rorim().set_bit());
+            return Err(nb::Error::Other(SlaveError::Overrun));
+        }
+        if self.spi.status.read().rne().bit_is_clear() {
+            return Err(nb::Error::WouldBlock);
+        }
+        self.start_reported = false;
+        Ok(WORD::from_data(self.spi.data.read().bits()))
+    }
+
+    /// Fill the buffer with received words. The timer is started with the given timeout
+    /// and the function returns [`SlaveError::Timeout`] with the number of received words
+    /// if the timer expires before the buffer was filled
+    pub fn read_timeout<T: CountDown>(
+        &mut self,
+        buf: &mut [WORD],
+        timer: &mut T,
+        timeout: impl Into<T::Time>,
+    ) -> Result<(), SlaveError> {
+        timer.start(timeout);
+        let mut received = 0;
+        while received < buf.len() {
+            match self.read() {
+                Ok(word) => {
+                    buf[received] = word;
+                    received += 1;
+                }
+                Err(nb::Error::Other(err)) => return Err(err),
+                Err(nb::Error::WouldBlock) => {
+                    if timer.wait().is_ok() {
+                        return Err(SlaveError::Timeout { received });
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Detect edges of the slave select signal with the block mode start status, which marks
+    /// the first word of every frame in the RX FIFO.
+    ///
+    /// The events are reported in the order of the received data, so the received words need
+    /// to be read with [`read`](Self::read) in between. [`CsEvent::Asserted`] is reported
+    /// once the first word of a frame is the next word in the RX FIFO. [`CsEvent::Deasserted`]
+    /// is reported before the start of the next frame, or once all words of the frame were
+    /// read and the peripheral is not busy anymore. Because the frame marker is stored together
+    /// with the received words, short and back-to-back frames are reported independently of
+    /// the polling interval
+    pub fn cs_event(&mut self) -> Option<CsEvent> {
+        let status = self.spi.status.read();
+        let rx_not_empty = status.rne().bit_is_set();
+        if rx_not_empty && status.rxdatafirst().bit_is_set() {
+            if self.start_reported {
+                return None;
+            }
+            if self.in_frame {
+                // The previous frame ended before the pending word was received
+                self.in_frame = false;
+                return Some(CsEvent::Deasserted);
+            }
+            self.in_frame = true;
+            self.start_reported = true;
+            return Some(CsEvent::Asserted);
+        }
+        if self.in_frame && !rx_not_empty && status.busy().bit_is_clear() {
+            self.in_frame = false;
+            return Some(CsEvent::Deasserted);
+        }
+        None
+    }
+
+    /// Returns [true] if the next word in the RX FIFO is the first word which was received
+    /// after the slave select signal was asserted
+    #[inline]
+    pub fn first_word_pending(&self) -> bool {
+        self.spi.status.read().rxdatafirst().bit_is_set()
+    }
+
+    /// Clear the RX and TX FIFO
+    pub fn clear_fifos(&mut self) {
+        self.spi.fifo_clr.write(|w| {
+            w.rxfifo().set_bit();
+            w.txfifo().set_bit()
+        });
+        self.start_reported = false;
+    }
+
+    pub fn listen(&mut self, event: SlaveEvent) {
+        self.spi.irq_enb.modify(|_, w| match event {
+            SlaveEvent::RxOverrun => w.rorim().set_bit(),
+            SlaveEvent::RxTimeout => w.rtim().set_bit(),
+            SlaveEvent::RxFifoHalfFull => w.rxim().set_bit(),
+            SlaveEvent::TxFifoHalfEmpty => w.txim().set_bit(),
+        });
+    }
+
+    pub fn unlisten(&mut self, event: SlaveEvent) {
+        self.spi.irq_enb.modify(|_, w| match event {
+            SlaveEvent::RxOverrun => w.rorim().clear_bit(),
+            SlaveEvent::RxTimeout => w.rtim().clear_bit(),
+            SlaveEvent::RxFifoHalfFull => w.rxim().clear_bit(),
+            SlaveEvent::TxFifoHalfEmpty => w.txim().clear_bit(),
+        });
+    }
+
+    /// Route the SPI interrupt to the given NVIC interrupt using the IRQSEL peripheral.
+    /// This also activates the peripheral clock for IRQSEL.
+    ///
+    /// The NVIC interrupt is not unmasked, this needs to be done by the caller
+    pub fn route_irq(&mut self, syscfg: &mut SYSCONFIG, irqsel: &mut IRQSEL, interrupt: Interrupt) {
+        route_irq::<SPI>(syscfg, irqsel, interrupt);
+    }
+
+    /// Remove the interrupt routing of the SPI peripheral
+    pub fn unroute_irq(&mut self, irqsel: &mut IRQSEL) {
+        unroute_irq::<SPI>(irqsel);
+    }
+
+    /// Start an interrupt-driven transfer. The TX words are shifted out to the master while the
+    /// received words are written into the RX buffer. The transfer is completed when the RX
+    /// buffer is full or when the RX timeout occurs after at least one word was received.
+    ///
+    /// A previous transfer is aborted with [`take_transfer`](Self::take_transfer). Words which
+    /// do not fit into the TX FIFO are written in [`on_interrupt`](Self::on_interrupt), which
+    /// needs to be called in the interrupt handler the SPI interrupt was routed to with
+    /// [`route_irq`](Self::route_irq)
+    pub fn start_transfer(&mut self, tx: &'static [WORD], rx: &'static mut [WORD]) {
+        self.take_transfer();
+        self.clear_fifos();
+        self.spi.irq_clr.write(|w| {
+            w.rorim().set_bit();
+            w.rtim().set_bit()
+        });
+        let tx_idx = self.preload(tx);
+        self.transfer = Some(IrqTransfer {
+            tx,
+            tx_idx,
+            rx,
+            rx_idx: 0,
+            overrun: false,
+            completed: false,
+        });
+        self.spi
+            .rxfifoirqtrg
+            .write(|w| unsafe { w.bits(FIFO_DEPTH as u32 / 2) });
+        self.spi
+            .txfifoirqtrg
+            .write(|w| unsafe { w.bits(FIFO_DEPTH as u32 / 2) });
+        self.spi.irq_enb.write(|w| {
+            w.rorim().set_bit();
+            w.rtim().set_bit();
+            w.rxim().set_bit();
+            w.txim().bit(tx_idx < tx.len())
+        });
+    }
+
+    /// This function should be called in the interrupt handler the SPI interrupt was routed to.
+    ///
+    /// Returns the number of received words once the transfer is completed and
+    /// [nb::Error::WouldBlock] while it is still in progress. [`SlaveError::Overrun`] is returned
+    /// on completion if received words were lost
+    pub fn on_interrupt(&mut self) -> nb::Result<usize, SlaveError> {
+        let spi = unsafe { &(*SPI::ptr()) };
+        let irq_end = spi.irq_end.read();
+        let transfer = match self.transfer.as_mut() {
+            Some(transfer) if !transfer.completed => transfer,
+            _ => return Err(nb::Error::WouldBlock),
+        };
+        if irq_end.rorim().bit_is_set() {
+            transfer.overrun = true;
+        }
+        while spi.status.read().rne().bit_is_set() {
+            let word = WORD::from_data(spi.data.read().bits());
+            if transfer.rx_idx < transfer.rx.len() {
+                transfer.rx[transfer.rx_idx] = word;
+                transfer.rx_idx += 1;
+            }
+        }
+        while transfer.tx_idx < transfer.tx.len() && spi.status.read().tnf().bit_is_set() {
+            spi.data
+                .write(|w| unsafe { w.bits(transfer.tx[transfer.tx_idx].to_data()) });
+            transfer.tx_idx += 1;
+        }
+        spi.irq_clr.write(|w| {
+            w.rorim().set_bit();
+            w.rtim().set_bit()
+        });
+        let timeout = irq_end.rtim().bit_is_set() && transfer.rx_idx > 0;
+        if transfer.tx_idx >= transfer.tx.len() {
+            spi.irq_enb.modify(|_, w| w.txim().clear_bit());
+        }
+        if transfer.rx_idx < transfer.rx.len() && !timeout {
+            return Err(nb::Error::WouldBlock);
+        }
+        transfer.completed = true;
+        spi.irq_enb.reset();
+        if transfer.overrun {
+            return Err(nb::Error::Other(SlaveError::Overrun));
+        }
+        Ok(transfer.rx_idx)
+    }
+
+    /// Return the buffers and the number of received words of the current interrupt-driven
+    /// transfer. A transfer which is still in progress is aborted. Returns [None] if no
+    /// transfer was started
+    pub fn take_transfer(&mut self) -> Option<(&'static [WORD], &'static mut [WORD], usize)> {
+        self.spi.irq_enb.reset();
+        self.transfer
+            .take()
+            .map(|transfer| (transfer.tx, transfer.rx, transfer.rx_idx))
+    }
+
+    /// Returns [true] if the interrupt-driven transfer was completed. The buffers can then be
+    /// retrieved with [`take_transfer`](Self::take_transfer)
+    pub fn transfer_completed(&self) -> bool {
+        matches!(&self.transfer, Some(transfer) if transfer.completed)
+    }
+}
@@ -0,0 +1,65 @@
+//! # SPI master timing
+//!
+//! The [`TimingConfig`] of a [`TransferConfig`](super::TransferConfig) tunes the timing of
+//! master transfers for each device:
+//!
+//! - Delayed capture: MISO is normally sampled with the clock edge which follows the edge the
+//!   slave shifted the bit out on, so the round trip delay must be below half an SPI clock
+//!   period. The round trip delay consists of the SCK propagation delay to the slave, the
+//!   output delay of the slave and the MISO propagation delay back to the master. At high SPI
+//!   clocks and with long cables, MISO needs to be sampled later with the delayed capture
+//!   feature. [`needs_delayed_capture`] checks whether this is required.
+//! - Pause between words: Without block mode, the hardware chip select is deasserted after
+//!   every word, which inserts a pause between the words. In block mode, the words are
+//!   transferred back to back. This is selected with the `blockmode` field of the transfer
+//!   configuration.
+//! - Chip select setup and hold times: The hardware chip selects are driven by the peripheral,
+//!   so their setup time can not be extended. The hold time can be extended in block mode,
+//!   because the frame is kept open with the block mode stall feature until the hold time
+//!   elapsed. Software chip selects of a [`SharedSpiDevice`](super::SharedSpiDevice) support
+//!   both.
+//!
+//! The delays are busy waits based on the system clock and are minimum values.
+use crate::time::Hertz;
+
+/// Timing configuration of SPI master transfers
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub struct TimingConfig {
+    /// Sample MISO with a delay. See [`needs_delayed_capture`] for when this is required. The
+    /// delayed capture is always enabled if the `mdlycap` field of the
+    /// [`SpiConfig`](super::SpiConfig) is set
+    pub delayed_capture: bool,
+    /// Minimum time between the assertion of a software chip select and the first clock edge.
+    /// This is not supported for hardware chip selects
+    pub cs_setup_ns: u32,
+    /// Minimum time between the end of the last word and the deassertion of the chip select.
+    /// Hardware chip selects only support this in block mode
+    pub cs_hold_ns: u32,
+}
+
+impl TimingConfig {
+    pub fn delayed_capture(mut self, enable: bool) -> Self {
+        self.delayed_capture = enable;
+        self
+    }
+
+    pub fn cs_setup_ns(mut self, ns: u32) -> Self {
+        self.cs_setup_ns = ns;
+        self
+    }
+
+    pub fn cs_hold_ns(mut self, ns: u32) -> Self {
+        self.cs_hold_ns = ns;
+        self
+    }
+}
+
+/// Returns [true] if the round trip delay in nanoseconds exceeds half a period of the SPI
+/// clock, so the delayed capture is required to sample MISO correctly.
+///
+/// Signals propagate with roughly 5 ns per meter of cable, so a cable of 2 m adds about 20 ns
+/// to the output delay of the slave. With such a cable, a slave with an output delay of 15 ns
+/// requires the delayed capture above an SPI clock of roughly 14 MHz
+pub fn needs_delayed_capture(spi_clk: Hertz, round_trip_ns: u32) -> bool {
+    2 * round_trip_ns as u64 * spi_clk.0 as u64 > 1_000_000_000
+}
@@ -0,0 +1,189 @@
+//! # Transfers with separate read and write buffers
+//!
+//! [`transfer_buffers`](SpiBase::transfer_buffers) writes the words of a write buffer and
+//! stores the received words in a read buffer which can have a different length.
+//! [`write_then_read`](SpiBase::write_then_read) writes a command first and reads the response
+//! afterwards, which is the usual access pattern of command/response devices. Once the write
+//! buffer is exhausted, the fill word configured with [`set_fill_word`](SpiBase::set_fill_word)
+//! is written.
+//!
+//! In block mode, the chip select is kept asserted for the whole transfer, including both
+//! phases of [`write_then_read`](SpiBase::write_then_read). The TX FIFO is preloaded while the
+//! transmission is paused with the MTXPAUSE bit and the block mode stall feature keeps the chip
+//! select asserted if the TX FIFO runs empty.
+use super::{Instance, SpiBase, Word, FIFO_DEPTH};
+
+/// Source and destination of the words of a transfer
+pub(super) trait TransferBuffers<WORD> {
+    /// Total number of words which are clocked out
+    fn len(&self) -> usize;
+    fn tx_word(&self, idx: usize) -> WORD;
+    fn store_rx_word(&mut self, idx: usize, word: WORD);
+}
+
+/// Separate buffers. The fill word is written if the read buffer is longer and the surplus
+/// received words are discarded if the write buffer is longer
+pub(super) struct SplitBuffers<'a, WORD> {
+    pub(super) read: &'a mut [WORD],
+    pub(super) write: &'a [WORD],
+    pub(super) fill: WORD,
+}
+
+impl<WORD: Word> TransferBuffers<WORD> for SplitBuffers<'_, WORD> {
+    fn len(&self) -> usize {
+        core::cmp::max(self.read.len(), self.write.len())
+    }
+
+    fn tx_word(&self, idx: usize) -> WORD {
+        self.write.get(idx).copied().unwrap_or(self.fill)
+    }
+
+    fn store_rx_word(&mut self, idx: usize, word: WORD) {
+        if let Some(slot) = self.read.get_mut(idx) {
+            *slot = word;
+        }
+    }
+}
+
+/// The words of the write buffer are written first. The words received during this phase are
+/// discarded. Afterwards, the fill word is written and the received words are stored in the
+/// read buffer
+struct WriteThenRead<'a, WORD> {
+    write: &'a [WORD],
+    read: &'a mut [WORD],
+    fill: WORD,
+}
+
+impl<WORD: Word> TransferBuffers<WORD> for WriteThenRead<'_, WORD> {
+    fn len(&self) -> usize {
+        self.write.len() + self.read.len()
+    }
+
+    fn tx_word(&self, idx: usize) -> WORD {
+        self.write.get(idx).copied().unwrap_or(self.fill)
+    }
+
+    fn store_rx_word(&mut self, idx: usize, word: WORD) {
+        if let Some(slot) = idx
+            .checked_sub(self.write.len())
+            .and_then(|idx| self.read.get_mut(idx))
+        {
+            *slot = word;
+        }
+    }
+}
+
+/// The written words are always ahead of the received words, so no word is overwritten before
+/// it was sent
+pub(super) struct InPlaceBuffer<'a, WORD>(pub(super) &'a mut [WORD]);
+
+impl<WORD: Word> TransferBuffers<WORD> for InPlaceBuffer<'_, WORD> {
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn tx_word(&self, idx: usize) -> WORD {
+        self.0[idx]
+    }
+
+    fn store_rx_word(&mut self, idx: usize, word: WORD) {
+        self.0[idx] = word;
+    }
+}
+
+impl<SPI: Instance, WORD: Word> SpiBase<SPI, WORD> {
+    /// Set the word which is written once the write buffer of a transfer is exhausted. The
+    /// default fill word is 0
+    #[inline]
+    pub fn set_fill_word(&mut self, fill_word: WORD) {
+        self.fill_word = fill_word;
+    }
+
+    #[inline]
+    pub fn fill_word(&self) -> WORD {
+        self.fill_word
+    }
+
+    /// Write the words of the write buffer and store the received words in the read buffer.
+    /// If the read buffer is longer, the fill word is written. If the write buffer is longer,
+    /// the surplus received words are discarded
+    pub fn transfer_buffers(&mut self, read: &mut [WORD], write: &[WORD]) {
+        let fill = self.fill_word;
+        self.in_block_frame(|spi| spi.transfer_words(SplitBuffers { read, write, fill }));
+    }
+
+    /// Write the words of the write buffer and fill the read buffer with the words which are
+    /// received afterwards. The words received while writing are discarded
+    pub fn write_then_read(&mut self, write: &[WORD], read: &mut [WORD]) {
+        let fill = self.fill_word;
+        self.in_block_frame(|spi| spi.transfer_words(WriteThenRead { write, read, fill }));
+    }
+
+    /// Keep the chip select asserted until the closure returns if block mode is enabled. The
+    /// frame ends once the peripheral is idle and the chip select hold time elapsed
+    pub(super) fn in_block_frame<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        if !self.blockmode {
+            return f(self);
+        }
+        self.spi.ctrl1.modify(|_, w| w.bmstall().set_bit());
+        let result = f(self);
+        self.wait_idle();
+        self.delay_ns(self.timing.cs_hold_ns);
+        // Clearing the stall bit ends the frame and deasserts the chip select
+        self.spi.ctrl1.modify(|_, w| w.bmstall().clear_bit());
+        result
+    }
+
+    /// Perform a blocking transfer. At most [`FIFO_DEPTH`] words are in flight, so the RX FIFO
+    /// can not overflow
+    pub(super) fn transfer_words(&mut self, mut bufs: impl TransferBuffers<WORD>) {
+        let len = bufs.len();
+        if len == 0 {
+            return;
+        }
+        let mut tx_idx = 0;
+        let mut rx_idx = 0;
+        if self.blockmode {
+            // Preload the FIFO so the first words are sent without gaps
+            self.spi.ctrl1.modify(|_, w| w.mtxpause().set_bit());
+            while tx_idx < len && self.spi.status.read().tnf().bit_is_set() {
+                let word = bufs.tx_word(tx_idx);
+                self.spi.data.write(|w| unsafe { w.bits(word.to_data()) });
+                tx_idx += 1;
+            }
+            self.spi.ctrl1.modify(|_, w| w.mtxpause().clear_bit());
+        }
+        while rx_idx < len {
+            while tx_idx < len
+                && tx_idx - rx_idx < FIFO_DEPTH
+                && self.spi.status.read().tnf().bit_is_set()
+            {
+                let word = bufs.tx_word(tx_idx);
+                self.spi.data.write(|w| unsafe { w.bits(word.to_data()) });
+                tx_idx += 1;
+            }
+            if self.spi.status.read().rne().bit_is_set() {
+                bufs.store_rx_word(rx_idx, WORD::from_data(self.spi.data.read().bits()));
+                rx_idx += 1;
+            }
+        }
+    }
+
+    /// Busy wait for the given number of nanoseconds
+    pub(super) fn delay_ns(&self, ns: u32) {
+        let cycles = (ns as u64 * self.sys_clk.0 as u64) / 1_000_000_000;
+        if cycles > 0 {
+            cortex_m::asm::delay(cycles as u32);
+        }
+    }
+
+    /// Blocks until the TX FIFO is empty and the peripheral is not busy anymore
+    pub(super) fn wait_idle(&self) {
+        loop {
+            let status = self.spi.status.read();
+            if status.tfe().bit_is_set() && status.busy().bit_is_clear() {
+                break;
+            }
+        }
+    }
+}
@@ -550,6 +550,24 @@ impl<TIM: ValidTim> CountDownTimer<TIM> {
         }
     }
 
+    /// Load the count down timer with a raw reset value in system clock cycles but do not
+    /// start it
+    pub fn load_count(&mut self, count: u32) {
+        self.tim.reg().ctrl.modify(|_, w| w.enable().clear_bit());
+        self.rst_val = count;
+        self.curr_freq = Hertz(self.sys_clk.0 / core::cmp::max(count, 1));
+        unsafe {
+            self.tim.reg().rst_value.write(|w| w.bits(count));
+            self.tim.reg().cnt_value.write(|w| w.bits(count));
+        }
+    }
+
+    /// Current value of the counter
+    #[inline(always)]
+    pub fn count(&self) -> u32 {
+        self.tim.reg().cnt_value.read().bits()
+    }
+
     #[inline(always)]
     pub fn enable(&mut self) {
         self.tim.reg().ctrl.modify(|_, w| w.enable().set_bit());
@@ -0,0 +1,163 @@
+//! # Async UART support
+//!
+//! With the `async` feature, [`Tx`], [`Rx`] and [`Uart`] implement the [`embedded_io_async`]
+//! traits. A task which has to wait for the FIFOs enables the matching UART interrupts and is
+//! woken from [`on_interrupt_async`], which needs to be called in the interrupt handler the UART
+//! interrupt was routed to with [`Uart::route_irq`].
+//!
+//! [`Read::read`] waits until at least one byte was received and then returns all bytes which
+//! are available in the RX FIFO. The RX FIFO trigger level interrupt and the RX timeout
+//! interrupt are used for this. [`Write::write`] waits until at least one byte could be
+//! written into the TX FIFO. [`Write::flush`] waits for the TX empty interrupt until the TX FIFO
+//! is empty and the transmitter is not busy anymore.
+use core::future::poll_fn;
+use core::task::Poll;
+
+use embedded_io_async::{Read, Write};
+
+use super::{tx_idle, Instance, Rx, Tx, Uart};
+use crate::waker::IrqWaker;
+
+static RX_WAKERS: [IrqWaker; 2] = [IrqWaker::new(), IrqWaker::new()];
+static TX_WAKERS: [IrqWaker; 2] = [IrqWaker::new(), IrqWaker::new()];
+
+/// This function should be called in the interrupt handler the UART interrupt was routed to if
+/// the async API is used. It masks the interrupts of the async API again and wakes the waiting
+/// tasks
+pub fn on_interrupt_async<UART: Instance>() {
+    let uart = unsafe { &(*UART::ptr()) };
+    let irq_end = uart.irq_end.read();
+    if irq_end.irq_rx().bit_is_set()
+        || irq_end.irq_rx_to().bit_is_set()
+        || irq_end.irq_rx_status().bit_is_set()
+    {
+        uart.irq_enb.modify(|_, w| {
+            w.irq_rx().clear_bit();
+            w.irq_rx_to().clear_bit();
+            w.irq_rx_status().clear_bit()
+        });
+        RX_WAKERS[UART::IDX as usize].wake();
+    }
+    if irq_end.irq_tx().bit_is_set() || irq_end.irq_tx_empty().bit_is_set() {
+        uart.irq_enb.modify(|_, w| {
+            w.irq_tx().clear_bit();
+            w.irq_tx_empty().clear_bit()
+        });
+        TX_WAKERS[UART::IDX as usize].wake();
+    }
+}
+
+/// Unmask the RX interrupts. The interrupt enable register is shared with the interrupt
+/// handler, so it is modified inside a critical section
+fn enable_rx_irqs<UART: Instance>() {
+    cortex_m::interrupt::free(|_| {
+        unsafe { &(*UART::ptr()) }.irq_enb.modify(|_, w| {
+            w.irq_rx().set_bit();
+            w.irq_rx_to().set_bit();
+            w.irq_rx_status().set_bit()
+        })
+    });
+}
+
+fn enable_tx_irq<UART: Instance>(tx_empty: bool) {
+    cortex_m::interrupt::free(|_| {
+        unsafe { &(*UART::ptr()) }.irq_enb.modify(|_, w| {
+            if tx_empty {
+                w.irq_tx_empty().set_bit()
+            } else {
+                w.irq_tx().set_bit()
+            }
+        })
+    });
+}
+
+impl<UART: Instance> Read for Rx<UART> {
+    /// Errors are only returned if no bytes were read yet. Otherwise, the bytes read so far
+    /// are returned and the error is returned by the next call
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let uart = unsafe { &(*UART::ptr()) };
+        poll_fn(|cx| {
+            let status = uart.rxstatus.read();
+            let pending = status.rdavl().bit_is_set()
+                || status.rxovr().bit_is_set()
+                || status.rxbrk().bit_is_set()
+                || status.rxfrm().bit_is_set()
+                || status.rxpar().bit_is_set();
+            if pending {
+                return Poll::Ready(());
+            }
+            RX_WAKERS[UART::IDX as usize].register(cx.waker());
+            enable_rx_irqs::<UART>();
+            // A byte which was received before the interrupts were enabled might not trigger
+            // an interrupt anymore
+            if uart.rxstatus.read().rdavl().bit_is_set() {
+                cx.waker().wake_by_ref();
+            }
+            Poll::Pending
+        })
+        .await;
+        // At least one byte or an error is pending now, so the blocking implementation returns
+        // immediately
+        embedded_io::Read::read(self, buf)
+    }
+}
+
+impl<UART: Instance> Write for Tx<UART> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let uart = unsafe { &(*UART::ptr()) };
+        poll_fn(|cx| {
+            if uart.txstatus.read().wrrdy().bit_is_set() {
+                return Poll::Ready(());
+            }
+            TX_WAKERS[UART::IDX as usize].register(cx.waker());
+            enable_tx_irq::<UART>(false);
+            if uart.txstatus.read().wrrdy().bit_is_set() {
+                cx.waker().wake_by_ref();
+            }
+            Poll::Pending
+        })
+        .await;
+        embedded_io::Write::write(self, buf)
+    }
+
+    /// Waits until the TX FIFO is empty and the transmitter is not busy anymore. The TX empty
+    /// interrupt is used for this
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        poll_fn(|cx| {
+            if tx_idle::<UART>() {
+                return Poll::Ready(Ok(()));
+            }
+            TX_WAKERS[UART::IDX as usize].register(cx.waker());
+            enable_tx_irq::<UART>(true);
+            if tx_idle::<UART>() {
+                cx.waker().wake_by_ref();
+            }
+            Poll::Pending
+        })
+        .await
+    }
+}
+
+impl<UART: Instance, PINS> Write for Uart<UART, PINS> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        Write::write(&mut self.tx, buf)
+            .await
+            .map_err(|e| match e {})
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        Write::flush(&mut self.tx).await.map_err(|e| match e {})
+    }
+}
+
+impl<UART: Instance, PINS> Read for Uart<UART, PINS> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        Read::read(&mut self.rx, buf).await
+    }
+}
@@ -0,0 +1,249 @@
+//! # Automatic baud rate detection
+//!
+//! [`Uart::autobaud`] measures the bit time of the sync character [`SYNC_CHAR`] (0x55) on the RX
+//! pin and configures the clock scale of the UART accordingly. The sync character produces an
+//! alternating bit pattern, so its start bit and data bits 1, 3 and 5 are low pulses which are
+//! exactly one bit time wide, independently of the parity setting.
+//!
+//! The measurement uses a timer which is clocked by the system clock. The RX pin is selected
+//! as an inverted cascade source with [`CascadeSource::PortABase`] or
+//! [`CascadeSource::PortBBase`], so the timer only counts down while the RX line is low.
+use crate::{
+    gpio::{DynGroup, DynPinId, Pin, PinId, PinMode},
+    time::{Bps, Hertz},
+    timer::{CascadeCtrl, CascadeSource, CountDownTimer, ValidTim},
+};
+
+use super::{ConfigError, Instance, Uart};
+
+/// Character which needs to be sent by the remote side for the baud rate detection
+pub const SYNC_CHAR: u8 = 0x55;
+
+/// Number of measured low pulses of the sync character which are one bit time wide
+const SYNC_PULSES: usize = 4;
+
+/// Maximum deviation of a single pulse from the average bit time in percent
+const MAX_PULSE_DEVIATION_PERCENT: u32 = 25;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum AutobaudError {
+    /// The sync character was not received within the given number of polls
+    Timeout,
+    /// The measured pulses do not match the bit pattern of the sync character
+    InvalidSyncChar,
+    /// The detected baud rate can not be configured
+    Config(ConfigError),
+}
+
+impl From<ConfigError> for AutobaudError {
+    fn from(err: ConfigError) -> Self {
+        AutobaudError::Config(err)
+    }
+}
+
+/// Pin tuples which provide the ID of the RX pin
+pub trait RxPinId {
+    const RX: DynPinId;
+}
+
+impl<TX, RX: PinId, M: PinMode> RxPinId for (TX, Pin<RX, M>) {
+    const RX: DynPinId = RX::DYN;
+}
+
+impl<TX, RX: PinId, M: PinMode, RTS, CTS> RxPinId for (TX, Pin<RX, M>, RTS, CTS) {
+    const RX: DynPinId = RX::DYN;
+}
+
+/// Calculate the bit time in system clock cycles from the cumulative counts measured at the end
+/// of each low pulse of the sync character. At least the four pulses of the sync character are
+/// required and every pulse needs to be within 25 % of the average bit time.
+///
+/// This function does not access any hardware and can be used and tested on the host as well
+pub fn bit_time_from_pulses(pulse_ends: &[u32]) -> Result<u32, AutobaudError> {
+    if pulse_ends.len() < SYNC_PULSES {
+        return Err(AutobaudError::InvalidSyncChar);
+    }
+    let total = *pulse_ends.last().ok_or(AutobaudError::InvalidSyncChar)?;
+    let bit_time = total / pulse_ends.len() as u32;
+    if bit_time == 0 {
+        return Err(AutobaudError::InvalidSyncChar);
+    }
+    let max_deviation = bit_time * MAX_PULSE_DEVIATION_PERCENT / 100;
+    let mut last = 0;
+    for pulse_end in pulse_ends {
+        let width = pulse_end
+            .checked_sub(last)
+            .ok_or(AutobaudError::InvalidSyncChar)?;
+        if width.abs_diff(bit_time) > max_deviation {
+            return Err(AutobaudError::InvalidSyncChar);
+        }
+        last = *pulse_end;
+    }
+    Ok(bit_time)
+}
+
+/// Convert a bit time in system clock cycles into a baud rate, rounded to the nearest integer
+pub fn baudrate_from_bit_time(sys_clk: Hertz, bit_time_cycles: u32) -> Bps {
+    let bit_time_cycles = core::cmp::max(bit_time_cycles, 1);
+    Bps((sys_clk.0 + bit_time_cycles / 2) / bit_time_cycles)
+}
+
+impl<UART: Instance, PINS: RxPinId> Uart<UART, PINS> {
+    /// Detect the baud rate by measuring the sync character [`SYNC_CHAR`] which needs to be sent
+    /// by the remote side and configure the UART with the detected baud rate.
+    ///
+    /// The receiver is disabled during the measurement and the RX FIFO is cleared afterwards.
+    /// The measurement is aborted with [`AutobaudError::Timeout`] after `max_polls` reads of
+    /// the timer counter. The timer is disabled and its cascade configuration is reset on return.
+    ///
+    /// The polling loop needs to be faster than one bit time, so very high baud rates in
+    /// relation to the system clock can not be detected
+    pub fn autobaud<TIM: ValidTim>(
+        &mut self,
+        timer: &mut CountDownTimer<TIM>,
+        max_polls: u32,
+    ) -> Result<Bps, AutobaudError> {
+        let rx_enabled = self.uart.enable.read().rxenable().bit_is_set();
+        self.uart.enable.modify(|_, w| w.rxenable().clear_bit());
+
+        let result = Self::measure_bit_time(timer, max_polls);
+
+        timer.disable();
+        timer.cascade_control(CascadeCtrl::default());
+        self.uart.fifo_clr.write(|w| {
+            w.rxfifo().set_bit();
+            w.rxsts().set_bit()
+        });
+        self.uart.enable.modify(|_, w| w.rxenable().bit(rx_enabled));
+
+        let baudrate = baudrate_from_bit_time(self.sys_clk, result?);
+        self.set_baudrate(baudrate)?;
+        Ok(baudrate)
+    }
+
+    fn measure_bit_time<TIM: ValidTim>(
+        timer: &mut CountDownTimer<TIM>,
+        max_polls: u32,
+    ) -> Result<u32, AutobaudError> {
+        let (src, num) = match PINS::RX.group {
+            DynGroup::A => (CascadeSource::PortABase, PINS::RX.num),
+            DynGroup::B => (CascadeSource::PortBBase, PINS::RX.num),
+        };
+        timer
+            .cascade_0_source(src, Some(num))
+            .map_err(|_| AutobaudError::InvalidSyncChar)?;
+        // Only count while the RX line is low
+        timer.cascade_control(CascadeCtrl {
+            enb_start_src_csd0: true,
+            inv_csd0: true,
+            ..Default::default()
+        });
+        timer.load_count(u32::MAX);
+        timer.enable();
+
+        let mut pulse_ends = [0; SYNC_PULSES];
+        let mut pulses = 0;
+        let mut in_pulse = false;
+        let mut last_cnt = timer.count();
+        for _ in 0..max_polls {
+            let cnt = timer.count();
+            let counting = cnt != last_cnt;
+            if counting && !in_pulse {
+                in_pulse = true;
+            } else if !counting && in_pulse {
+                // The counter is frozen while the line is high, so the count is exact
+                in_pulse = false;
+                pulse_ends[pulses] = u32::MAX - cnt;
+                pulses += 1;
+                if pulses == SYNC_PULSES {
+                    return bit_time_from_pulses(&pulse_ends);
+                }
+            }
+            last_cnt = cnt;
+        }
+        Err(AutobaudError::Timeout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SYS_CLK: Hertz = Hertz(50_000_000);
+
+    /// Cumulative pulse ends for the given pulse widths
+    fn pulse_ends(widths: [u32; SYNC_PULSES]) -> [u32; SYNC_PULSES] {
+        let mut end = 0;
+        widths.map(|width| {
+            end += width;
+            end
+        })
+    }
+
+    #[test]
+    fn valid_sync_char() {
+        // 9600 baud at 50 MHz are 5208.33 cycles per bit
+        let bit_time = bit_time_from_pulses(&[5208, 10417, 15625, 20833]).unwrap();
+        assert_eq!(bit_time, 5208);
+        assert_eq!(baudrate_from_bit_time(SYS_CLK, bit_time), Bps(9601));
+
+        let bit_time = bit_time_from_pulses(&pulse_ends([434; SYNC_PULSES])).unwrap();
+        assert_eq!(bit_time, 434);
+        assert_eq!(baudrate_from_bit_time(SYS_CLK, bit_time), Bps(115_207));
+    }
+
+    #[test]
+    fn pulse_deviation() {
+        // The average bit time is 5208 cycles, so the maximum deviation is 1302 cycles
+        assert_eq!(
+            bit_time_from_pulses(&pulse_ends([4000, 5208, 5208, 6416])),
+            Ok(5208)
+        );
+        assert_eq!(
+            bit_time_from_pulses(&pulse_ends([3800, 5208, 5208, 6616])),
+            Err(AutobaudError::InvalidSyncChar)
+        );
+        // One pulse is two bit times wide, so the character was not the sync character
+        assert_eq!(
+            bit_time_from_pulses(&pulse_ends([5208, 5208, 5208, 10416])),
+            Err(AutobaudError::InvalidSyncChar)
+        );
+    }
+
+    #[test]
+    fn non_monotonic_pulse_ends() {
+        assert_eq!(
+            bit_time_from_pulses(&[5208, 10417, 10000, 20833]),
+            Err(AutobaudError::InvalidSyncChar)
+        );
+    }
+
+    #[test]
+    fn empty_or_short_pulses() {
+        assert_eq!(
+            bit_time_from_pulses(&[]),
+            Err(AutobaudError::InvalidSyncChar)
+        );
+        assert_eq!(
+            bit_time_from_pulses(&[5208]),
+            Err(AutobaudError::InvalidSyncChar)
+        );
+        assert_eq!(
+            bit_time_from_pulses(&[5208, 10417, 15625]),
+            Err(AutobaudError::InvalidSyncChar)
+        );
+        // Pulses which are too short to be measured
+        assert_eq!(
+            bit_time_from_pulses(&[0, 1, 2, 3]),
+            Err(AutobaudError::InvalidSyncChar)
+        );
+    }
+
+    #[test]
+    fn baudrate_is_rounded() {
+        assert_eq!(baudrate_from_bit_time(SYS_CLK, 5000), Bps(10_000));
+        assert_eq!(baudrate_from_bit_time(SYS_CLK, 3), Bps(16_666_667));
+        // A bit time of zero is treated as one cycle
+        assert_eq!(baudrate_from_bit_time(SYS_CLK, 0), Bps(50_000_000));
+    }
+}
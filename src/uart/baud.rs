@@ -0,0 +1,272 @@
+//! # Baud rate calculation
+//!
+//! The UART baud rate is derived from the system clock with a fractional clock divisor which
+//! has 6 fractional bits. The functions in this module only use integer arithmetic, do not
+//! access any hardware and can be used and tested on the host as well.
+use crate::time::{Bps, Hertz};
+
+use super::ConfigError;
+
+/// Maximum value of the integer part of the clock divisor. The clock scale register provides
+/// 18 bits for the integer part
+pub const MAX_CLK_DIV_INTEGER: u32 = (1 << 18) - 1;
+
+/// Oversampling rate of the receiver
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Oversampling {
+    /// Standard 16x baud clock
+    X16,
+    /// 8x baud clock (BAUD8 mode). This allows higher baud rates but makes the receiver more
+    /// susceptible to noise
+    X8,
+    /// Select the oversampling rate with the smaller baud rate error. The 16x baud clock is
+    /// preferred if both rates yield the same error
+    Auto,
+}
+
+/// Result of a baud rate calculation
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct BaudDivisor {
+    /// Integer part of the clock divisor
+    pub integer: u32,
+    /// Fractional part of the clock divisor in steps of 1/64
+    pub frac: u8,
+    /// The 8x baud clock is used
+    pub baud8: bool,
+    /// Baud rate which is actually achieved with this divisor
+    pub achieved_baudrate: Bps,
+    /// Relative error of the achieved baud rate in parts per million
+    pub error_ppm: u32,
+}
+
+impl BaudDivisor {
+    /// Value of the clock scale register
+    #[inline]
+    pub fn clk_scale(&self) -> u32 {
+        (self.integer << 6) | self.frac as u32
+    }
+
+    /// Relative error of the achieved baud rate in percent
+    #[inline]
+    pub fn error_percent(&self) -> f32 {
+        self.error_ppm as f32 / 10_000.0
+    }
+}
+
+/// Calculate the clock divisor for a given system clock and target baud rate.
+///
+/// Returns [`ConfigError::BaudrateUnreachable`] if the baud rate is 0 or the divisor can not be
+/// represented by the clock scale register
+pub fn calculate_baud_divisor(
+    sys_clk: Hertz,
+    baudrate: Bps,
+    oversampling: Oversampling,
+) -> Result<BaudDivisor, ConfigError> {
+    match oversampling {
+        Oversampling::X16 => baud_divisor(sys_clk, baudrate, false),
+        Oversampling::X8 => baud_divisor(sys_clk, baudrate, true),
+        Oversampling::Auto => {
+            match (
+                baud_divisor(sys_clk, baudrate, false),
+                baud_divisor(sys_clk, baudrate, true),
+            ) {
+                (Ok(x16), Ok(x8)) => {
+                    if x8.error_ppm < x16.error_ppm {
+                        Ok(x8)
+                    } else {
+                        Ok(x16)
+                    }
+                }
+                (Ok(x16), Err(_)) => Ok(x16),
+                (Err(_), Ok(x8)) => Ok(x8),
+                (Err(e), Err(_)) => Err(e),
+            }
+        }
+    }
+}
+
+/// Calculate the clock divisor and verify that the baud rate error does not exceed the given
+/// tolerance.
+///
+/// Returns [`ConfigError::BaudrateErrorTooLarge`] if the tolerance is exceeded
+pub fn calculate_baud_divisor_checked(
+    sys_clk: Hertz,
+    baudrate: Bps,
+    oversampling: Oversampling,
+    tolerance_ppm: u32,
+) -> Result<BaudDivisor, ConfigError> {
+    let divisor = calculate_baud_divisor(sys_clk, baudrate, oversampling)?;
+    if divisor.error_ppm > tolerance_ppm {
+        return Err(ConfigError::BaudrateErrorTooLarge {
+            error_ppm: divisor.error_ppm,
+        });
+    }
+    Ok(divisor)
+}
+
+fn baud_divisor(sys_clk: Hertz, baudrate: Bps, baud8: bool) -> Result<BaudDivisor, ConfigError> {
+    if baudrate.0 == 0 {
+        return Err(ConfigError::BaudrateUnreachable);
+    }
+    let baud_multiplier: u64 = match baud8 {
+        false => 16,
+        true => 8,
+    };
+    let sys_clk_scaled = sys_clk.0 as u64 * 64;
+    let denominator = baudrate.0 as u64 * baud_multiplier;
+    // Divisor with 6 fractional bits, rounded to the nearest value
+    let clk_scale = (sys_clk_scaled + denominator / 2) / denominator;
+    let integer = clk_scale >> 6;
+    if integer == 0 || integer > MAX_CLK_DIV_INTEGER as u64 {
+        return Err(ConfigError::BaudrateUnreachable);
+    }
+    let achieved_denominator = clk_scale * baud_multiplier;
+    let achieved = (sys_clk_scaled + achieved_denominator / 2) / achieved_denominator;
+    let target = baudrate.0 as u64;
+    let diff = achieved.abs_diff(target);
+    Ok(BaudDivisor {
+        integer: integer as u32,
+        frac: (clk_scale & 0x3f) as u8,
+        baud8,
+        achieved_baudrate: Bps(achieved as u32),
+        error_ppm: ((diff * 1_000_000) / target) as u32,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uart::Config;
+
+    const SYS_CLK: Hertz = Hertz(50_000_000);
+
+    #[test]
+    fn exact_divisor() {
+        let divisor = calculate_baud_divisor(SYS_CLK, Bps(9600), Oversampling::X16).unwrap();
+        assert_eq!(divisor.integer, 325);
+        assert_eq!(divisor.frac, 33);
+        assert!(!divisor.baud8);
+        assert_eq!(divisor.achieved_baudrate, Bps(9600));
+        assert_eq!(divisor.error_ppm, 0);
+
+        let divisor = calculate_baud_divisor(SYS_CLK, Bps(2_000_000), Oversampling::X16).unwrap();
+        assert_eq!((divisor.integer, divisor.frac), (1, 36));
+        assert_eq!(divisor.clk_scale(), 100);
+        assert_eq!(divisor.error_ppm, 0);
+    }
+
+    #[test]
+    fn fractional_divisor_is_rounded() {
+        let divisor = calculate_baud_divisor(SYS_CLK, Bps(115_200), Oversampling::X16).unwrap();
+        assert_eq!((divisor.integer, divisor.frac), (27, 8));
+        assert_eq!(divisor.clk_scale(), (27 << 6) | 8);
+        assert_eq!(divisor.achieved_baudrate, Bps(115_207));
+        assert_eq!(divisor.error_ppm, 60);
+
+        let divisor = calculate_baud_divisor(SYS_CLK, Bps(115_200), Oversampling::X8).unwrap();
+        assert_eq!((divisor.integer, divisor.frac), (54, 16));
+        assert!(divisor.baud8);
+        assert_eq!(divisor.achieved_baudrate, Bps(115_207));
+    }
+
+    #[test]
+    fn auto_oversampling_selects_smaller_error() {
+        let x16 = calculate_baud_divisor(SYS_CLK, Bps(3_000_000), Oversampling::X16).unwrap();
+        assert_eq!(x16.error_ppm, 4975);
+        let x8 = calculate_baud_divisor(SYS_CLK, Bps(3_000_000), Oversampling::X8).unwrap();
+        assert_eq!(x8.error_ppm, 2506);
+        let auto = calculate_baud_divisor(SYS_CLK, Bps(3_000_000), Oversampling::Auto).unwrap();
+        assert_eq!(auto, x8);
+    }
+
+    #[test]
+    fn auto_oversampling_prefers_x16_for_equal_error() {
+        let auto = calculate_baud_divisor(SYS_CLK, Bps(115_200), Oversampling::Auto).unwrap();
+        assert!(!auto.baud8);
+        assert_eq!(auto.error_ppm, 60);
+    }
+
+    #[test]
+    fn auto_oversampling_falls_back_to_x8() {
+        let sys_clk = Hertz(10_000_000);
+        assert_eq!(
+            calculate_baud_divisor(sys_clk, Bps(1_000_000), Oversampling::X16),
+            Err(ConfigError::BaudrateUnreachable)
+        );
+        let auto = calculate_baud_divisor(sys_clk, Bps(1_000_000), Oversampling::Auto).unwrap();
+        assert!(auto.baud8);
+        assert_eq!((auto.integer, auto.frac), (1, 16));
+        assert_eq!(auto.error_ppm, 0);
+    }
+
+    #[test]
+    fn unreachable_baudrates() {
+        for oversampling in [Oversampling::X16, Oversampling::X8, Oversampling::Auto] {
+            assert_eq!(
+                calculate_baud_divisor(SYS_CLK, Bps(0), oversampling),
+                Err(ConfigError::BaudrateUnreachable)
+            );
+            // The integer part of the divisor exceeds 18 bits
+            assert_eq!(
+                calculate_baud_divisor(SYS_CLK, Bps(1), oversampling),
+                Err(ConfigError::BaudrateUnreachable)
+            );
+            // The integer part of the divisor is 0
+            assert_eq!(
+                calculate_baud_divisor(SYS_CLK, Bps(10_000_000), oversampling),
+                Err(ConfigError::BaudrateUnreachable)
+            );
+        }
+    }
+
+    #[test]
+    fn largest_integer_divisor() {
+        let sys_clk = Hertz(16 * MAX_CLK_DIV_INTEGER);
+        let divisor = calculate_baud_divisor(sys_clk, Bps(1), Oversampling::X16).unwrap();
+        assert_eq!(divisor.integer, MAX_CLK_DIV_INTEGER);
+        assert_eq!(divisor.frac, 0);
+        assert_eq!(
+            calculate_baud_divisor(Hertz(sys_clk.0 + 16), Bps(1), Oversampling::X16),
+            Err(ConfigError::BaudrateUnreachable)
+        );
+    }
+
+    #[test]
+    fn tolerance_check() {
+        let checked = |tolerance_ppm| {
+            calculate_baud_divisor_checked(
+                SYS_CLK,
+                Bps(3_000_000),
+                Oversampling::X16,
+                tolerance_ppm,
+            )
+        };
+        // The tolerance is inclusive
+        assert_eq!(checked(4975).unwrap().error_ppm, 4975);
+        assert_eq!(
+            checked(4974),
+            Err(ConfigError::BaudrateErrorTooLarge { error_ppm: 4975 })
+        );
+        assert_eq!(
+            checked(0),
+            Err(ConfigError::BaudrateErrorTooLarge { error_ppm: 4975 })
+        );
+        assert_eq!(
+            calculate_baud_divisor_checked(SYS_CLK, Bps(0), Oversampling::X16, u32::MAX),
+            Err(ConfigError::BaudrateUnreachable)
+        );
+    }
+
+    #[test]
+    fn default_config_uses_auto_oversampling() {
+        let config = Config::default();
+        assert_eq!(config.oversampling, Oversampling::Auto);
+        assert!(calculate_baud_divisor_checked(
+            SYS_CLK,
+            config.baudrate,
+            config.oversampling,
+            config.baud_tolerance_ppm
+        )
+        .is_ok());
+    }
+}
@@ -0,0 +1,598 @@
+//! # Interrupt-driven buffered UART
+//!
+//! The [`BufferedUart`] uses the UART interrupts to fill and drain user-supplied ring buffers.
+//! This avoids losing received bytes when the application can not poll the 16 byte deep
+//! hardware FIFO in time.
+//!
+//! The UART interrupt needs to be routed to a NVIC interrupt with
+//! [`Uart::route_irq`](super::Uart::route_irq) and
+//! [`on_interrupt`](BufferedUart::on_interrupt) needs to be called inside the interrupt handler.
+//! The [`BufferedUart`] is usually shared between the interrupt handler and the application
+//! by placing it inside a [`cortex_m::interrupt::Mutex`].
+//!
+//! The register accesses are performed through the [`BufferedUartBackend`] trait, which is
+//! implemented for [`Uart`].
+use core::convert::Infallible;
+
+use embedded_hal::serial;
+
+use super::{tx_idle, Error, Instance, Uart};
+
+//==================================================================================================
+// Ring buffer
+//==================================================================================================
+
+/// Simple ring buffer on top of a user-provided static byte buffer.
+///
+/// This type does not access any hardware and can be used and tested on the host as well
+pub struct RingBuffer {
+    buf: &'static mut [u8],
+    read_idx: usize,
+    len: usize,
+}
+
+impl RingBuffer {
+    pub fn new(buf: &'static mut [u8]) -> Self {
+        RingBuffer {
+            buf,
+            read_idx: 0,
+            len: 0,
+        }
+    }
+
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    pub fn is_full(&self) -> bool {
+        self.len == self.buf.len()
+    }
+
+    /// Number of bytes which can still be pushed into the buffer
+    #[inline]
+    pub fn free(&self) -> usize {
+        self.buf.len() - self.len
+    }
+
+    /// Push a byte into the buffer. Returns [false] if the buffer is full and the byte was
+    /// discarded
+    pub fn push(&mut self, byte: u8) -> bool {
+        if self.is_full() {
+            return false;
+        }
+        let write_idx = (self.read_idx + self.len) % self.buf.len();
+        self.buf[write_idx] = byte;
+        self.len += 1;
+        true
+    }
+
+    /// Push as many bytes of the slice as possible into the buffer and return the number of
+    /// pushed bytes
+    pub fn push_slice(&mut self, bytes: &[u8]) -> usize {
+        let mut pushed = 0;
+        for byte in bytes {
+            if !self.push(*byte) {
+                break;
+            }
+            pushed += 1;
+        }
+        pushed
+    }
+
+    /// Returns the oldest byte in the buffer without removing it
+    pub fn peek(&self) -> Option<u8> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(self.buf[self.read_idx])
+    }
+
+    /// Removes and returns the oldest byte in the buffer
+    pub fn pop(&mut self) -> Option<u8> {
+        let byte = self.peek()?;
+        self.read_idx = (self.read_idx + 1) % self.buf.len();
+        self.len -= 1;
+        Some(byte)
+    }
+
+    /// Pop as many bytes as possible into the slice and return the number of read bytes
+    pub fn pop_slice(&mut self, bytes: &mut [u8]) -> usize {
+        let mut popped = 0;
+        for byte in bytes.iter_mut() {
+            match self.pop() {
+                Some(val) => *byte = val,
+                None => break,
+            }
+            popped += 1;
+        }
+        popped
+    }
+
+    pub fn clear(&mut self) {
+        self.read_idx = 0;
+        self.len = 0;
+    }
+
+    /// Release the underlying buffer
+    pub fn release(self) -> &'static mut [u8] {
+        self.buf
+    }
+}
+
+//==================================================================================================
+// Backend
+//==================================================================================================
+
+/// Register access required by the [`BufferedUart`].
+///
+/// This trait is implemented for [`Uart`]. It separates the buffering logic from the hardware,
+/// so the [`BufferedUart`] can be tested on the host with a mock UART
+pub trait BufferedUartBackend:
+    serial::Read<u8, Error = Error> + serial::Write<u8, Error = Infallible>
+{
+    /// Returns [true] if the RX FIFO contains data
+    fn rx_data_available(&self) -> bool;
+    /// Returns [true] as long as the TX FIFO contains data or the transmitter is busy
+    fn tx_busy(&self) -> bool;
+    /// Discard all bytes in the RX FIFO
+    fn clear_rx_fifo(&mut self);
+    /// Enable the RX FIFO, RX timeout and RX error interrupts
+    fn enable_rx_irqs(&mut self);
+    /// Enable or disable the TX FIFO interrupt
+    fn set_tx_irq(&mut self, enable: bool);
+    /// Disable all interrupts
+    fn disable_irqs(&mut self);
+}
+
+impl<UART: Instance, PINS> BufferedUartBackend for Uart<UART, PINS> {
+    #[inline]
+    fn rx_data_available(&self) -> bool {
+        self.uart.rxstatus.read().rdavl().bit_is_set()
+    }
+
+    #[inline]
+    fn tx_busy(&self) -> bool {
+        !tx_idle::<UART>()
+    }
+
+    #[inline]
+    fn clear_rx_fifo(&mut self) {
+        self.uart.fifo_clr.write(|w| w.rxfifo().set_bit());
+    }
+
+    fn enable_rx_irqs(&mut self) {
+        self.uart.irq_enb.modify(|_, w| {
+            w.irq_rx().set_bit();
+            w.irq_rx_to().set_bit();
+            w.irq_rx_status().set_bit()
+        });
+    }
+
+    #[inline]
+    fn set_tx_irq(&mut self, enable: bool) {
+        self.uart.irq_enb.modify(|_, w| w.irq_tx().bit(enable));
+    }
+
+    #[inline]
+    fn disable_irqs(&mut self) {
+        self.uart.irq_enb.reset();
+    }
+}
+
+//==================================================================================================
+// Buffered UART
+//==================================================================================================
+
+/// Interrupt-driven UART using ring buffers for reception and transmission. The backend is
+/// usually a [`Uart`]
+pub struct BufferedUart<B> {
+    uart: B,
+    rx_buf: RingBuffer,
+    tx_buf: RingBuffer,
+    rx_err: Option<Error>,
+}
+
+impl<B: BufferedUartBackend> BufferedUart<B> {
+    /// Create a new buffered UART. This enables the RX FIFO, RX timeout and RX error interrupts.
+    /// The TX interrupt is only enabled while the TX ring buffer contains data
+    pub fn new(mut uart: B, rx_buf: &'static mut [u8], tx_buf: &'static mut [u8]) -> Self {
+        uart.enable_rx_irqs();
+        uart.set_tx_irq(false);
+        BufferedUart {
+            uart,
+            rx_buf: RingBuffer::new(rx_buf),
+            tx_buf: RingBuffer::new(tx_buf),
+            rx_err: None,
+        }
+    }
+
+    /// This function should be called in the interrupt handler the UART interrupt was routed to.
+    /// It moves all received bytes into the RX ring buffer and refills the TX FIFO from the
+    /// TX ring buffer
+    pub fn on_interrupt(&mut self) {
+        loop {
+            match serial::Read::read(&mut self.uart) {
+                Ok(byte) => {
+                    if !self.rx_buf.push(byte) {
+                        self.rx_err = Some(Error::Overrun);
+                    }
+                }
+                Err(nb::Error::WouldBlock) => break,
+                Err(nb::Error::Other(err)) => {
+                    self.rx_err = Some(err);
+                    if !self.uart.rx_data_available() {
+                        break;
+                    }
+                }
+            }
+        }
+        self.fill_tx_fifo();
+    }
+
+    /// Read bytes from the RX ring buffer. Returns the number of read bytes, which might be 0.
+    ///
+    /// If an error occured during reception, including an overflow of the ring buffer,
+    /// the error is returned once and the read bytes remain in the ring buffer
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        if let Some(err) = self.rx_err.take() {
+            return Err(err);
+        }
+        Ok(self.rx_buf.pop_slice(buf))
+    }
+
+    /// Write bytes into the TX ring buffer and start the transmission. Returns the number of
+    /// bytes which were accepted, which might be smaller than the length of the slice if the
+    /// ring buffer is full
+    pub fn write(&mut self, buf: &[u8]) -> usize {
+        let written = self.tx_buf.push_slice(buf);
+        self.fill_tx_fifo();
+        written
+    }
+
+    /// Number of received bytes which can be read
+    #[inline]
+    pub fn rx_available(&self) -> usize {
+        self.rx_buf.len()
+    }
+
+    /// Number of bytes which can still be written to the TX ring buffer
+    #[inline]
+    pub fn tx_free(&self) -> usize {
+        self.tx_buf.free()
+    }
+
+    /// Discard all received data in the RX ring buffer and the RX FIFO
+    pub fn clear_rx(&mut self) {
+        self.uart.clear_rx_fifo();
+        self.rx_buf.clear();
+        self.rx_err = None;
+    }
+
+    /// Returns [nb::Error::WouldBlock] as long as the TX ring buffer contains data or the
+    /// transmitter is busy
+    pub fn flush(&mut self) -> nb::Result<(), Infallible> {
+        if !self.tx_buf.is_empty() || self.uart.tx_busy() {
+            return Err(nb::Error::WouldBlock);
+        }
+        Ok(())
+    }
+
+    /// Disable all UART interrupts and release the UART and the buffers
+    pub fn release(mut self) -> (B, &'static mut [u8], &'static mut [u8]) {
+        self.uart.disable_irqs();
+        (self.uart, self.rx_buf.release(), self.tx_buf.release())
+    }
+
+    fn fill_tx_fifo(&mut self) {
+        while let Some(byte) = self.tx_buf.peek() {
+            if serial::Write::write(&mut self.uart, byte).is_err() {
+                break;
+            }
+            self.tx_buf.pop();
+        }
+        // The TX interrupt is generated as long as the TX FIFO is below the trigger level,
+        // so it is only enabled while there is still data to send
+        let pending = !self.tx_buf.is_empty();
+        self.uart.set_tx_irq(pending);
+    }
+}
+
+impl<B: BufferedUartBackend> serial::Read<u8> for BufferedUart<B> {
+    type Error = Error;
+
+    fn read(&mut self) -> nb::Result<u8, Error> {
+        if let Some(err) = self.rx_err.take() {
+            return Err(nb::Error::Other(err));
+        }
+        self.rx_buf.pop().ok_or(nb::Error::WouldBlock)
+    }
+}
+
+impl<B: BufferedUartBackend> serial::Write<u8> for BufferedUart<B> {
+    type Error = Infallible;
+
+    fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        if !self.tx_buf.push(word) {
+            return Err(nb::Error::WouldBlock);
+        }
+        self.fill_tx_fifo();
+        Ok(())
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        BufferedUart::flush(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::{boxed::Box, collections::VecDeque, vec, vec::Vec};
+
+    use super::*;
+
+    fn static_buf(len: usize) -> &'static mut [u8] {
+        Box::leak(vec![0; len].into_boxed_slice())
+    }
+
+    /// Mock UART with a 16 byte deep TX FIFO. Bytes in the RX FIFO can carry an error
+    #[derive(Default)]
+    struct MockUart {
+        rx_fifo: VecDeque<Result<u8, Error>>,
+        tx_fifo: Vec<u8>,
+        tx_busy: bool,
+        rx_irqs: bool,
+        tx_irq: bool,
+    }
+
+    const MOCK_FIFO_DEPTH: usize = 16;
+
+    impl serial::Read<u8> for MockUart {
+        type Error = Error;
+
+        fn read(&mut self) -> nb::Result<u8, Error> {
+            match self.rx_fifo.pop_front() {
+                Some(Ok(byte)) => Ok(byte),
+                Some(Err(err)) => Err(nb::Error::Other(err)),
+                None => Err(nb::Error::WouldBlock),
+            }
+        }
+    }
+
+    impl serial::Write<u8> for MockUart {
+        type Error = Infallible;
+
+        fn write(&mut self, word: u8) -> nb::Result<(), Infallible> {
+            if self.tx_fifo.len() == MOCK_FIFO_DEPTH {
+                return Err(nb::Error::WouldBlock);
+            }
+            self.tx_fifo.push(word);
+            Ok(())
+        }
+
+        fn flush(&mut self) -> nb::Result<(), Infallible> {
+            Ok(())
+        }
+    }
+
+    impl BufferedUartBackend for MockUart {
+        fn rx_data_available(&self) -> bool {
+            !self.rx_fifo.is_empty()
+        }
+
+        fn tx_busy(&self) -> bool {
+            self.tx_busy
+        }
+
+        fn clear_rx_fifo(&mut self) {
+            self.rx_fifo.clear();
+        }
+
+        fn enable_rx_irqs(&mut self) {
+            self.rx_irqs = true;
+        }
+
+        fn set_tx_irq(&mut self, enable: bool) {
+            self.tx_irq = enable;
+        }
+
+        fn disable_irqs(&mut self) {
+            self.rx_irqs = false;
+            self.tx_irq = false;
+        }
+    }
+
+    fn buffered_uart(rx_len: usize, tx_len: usize) -> BufferedUart<MockUart> {
+        BufferedUart::new(MockUart::default(), static_buf(rx_len), static_buf(tx_len))
+    }
+
+    #[test]
+    fn ring_buffer_empty() {
+        let mut ring = RingBuffer::new(static_buf(4));
+        assert!(ring.is_empty());
+        assert_eq!(ring.free(), 4);
+        assert_eq!(ring.peek(), None);
+        assert_eq!(ring.pop(), None);
+        let mut out = [0; 4];
+        assert_eq!(ring.pop_slice(&mut out), 0);
+    }
+
+    #[test]
+    fn ring_buffer_full_drops_bytes() {
+        let mut ring = RingBuffer::new(static_buf(4));
+        assert_eq!(ring.push_slice(&[1, 2, 3, 4, 5, 6]), 4);
+        assert!(ring.is_full());
+        assert_eq!(ring.free(), 0);
+        assert!(!ring.push(7));
+        let mut out = [0; 8];
+        assert_eq!(ring.pop_slice(&mut out), 4);
+        assert_eq!(out[..4], [1, 2, 3, 4]);
+        assert!(ring.is_empty());
+    }
+
+    #[test]
+    fn ring_buffer_wrap_around() {
+        let mut ring = RingBuffer::new(static_buf(4));
+        assert_eq!(ring.push_slice(&[1, 2, 3]), 3);
+        assert_eq!(ring.pop(), Some(1));
+        assert_eq!(ring.pop(), Some(2));
+        // The write index wraps around to the start of the buffer
+        assert_eq!(ring.push_slice(&[4, 5, 6]), 3);
+        assert!(ring.is_full());
+        assert_eq!(ring.peek(), Some(3));
+        let mut out = [0; 4];
+        assert_eq!(ring.pop_slice(&mut out), 4);
+        assert_eq!(out, [3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn ring_buffer_clear() {
+        let mut ring = RingBuffer::new(static_buf(4));
+        ring.push_slice(&[1, 2, 3]);
+        ring.pop();
+        ring.clear();
+        assert!(ring.is_empty());
+        assert_eq!(ring.push_slice(&[7, 8, 9, 10]), 4);
+        assert_eq!(ring.pop(), Some(7));
+    }
+
+    #[test]
+    fn new_enables_only_rx_irqs() {
+        let uart = buffered_uart(8, 8);
+        assert!(uart.uart.rx_irqs);
+        assert!(!uart.uart.tx_irq);
+    }
+
+    #[test]
+    fn on_interrupt_moves_rx_fifo_to_ring_buffer() {
+        let mut uart = buffered_uart(8, 8);
+        uart.uart.rx_fifo.extend([Ok(1), Ok(2), Ok(3)]);
+        uart.on_interrupt();
+        assert!(uart.uart.rx_fifo.is_empty());
+        assert_eq!(uart.rx_available(), 3);
+        let mut out = [0; 2];
+        assert_eq!(uart.read(&mut out), Ok(2));
+        assert_eq!(out, [1, 2]);
+        assert_eq!(uart.read(&mut out), Ok(1));
+        assert_eq!(out[0], 3);
+        assert_eq!(uart.read(&mut out), Ok(0));
+    }
+
+    #[test]
+    fn on_interrupt_rx_ring_buffer_overflow() {
+        let mut uart = buffered_uart(4, 8);
+        uart.uart.rx_fifo.extend((0..6).map(Ok));
+        uart.on_interrupt();
+        // The FIFO is always drained, the surplus bytes are dropped
+        assert!(uart.uart.rx_fifo.is_empty());
+        assert_eq!(uart.rx_available(), 4);
+        let mut out = [0; 8];
+        assert_eq!(uart.read(&mut out), Err(Error::Overrun));
+        assert_eq!(uart.read(&mut out), Ok(4));
+        assert_eq!(out[..4], [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn on_interrupt_rx_error_is_reported_once() {
+        let mut uart = buffered_uart(8, 8);
+        uart.uart
+            .rx_fifo
+            .extend([Ok(1), Err(Error::FramingError), Ok(2)]);
+        uart.on_interrupt();
+        assert_eq!(uart.rx_available(), 2);
+        let mut out = [0; 4];
+        assert_eq!(uart.read(&mut out), Err(Error::FramingError));
+        assert_eq!(uart.read(&mut out), Ok(2));
+        assert_eq!(out[..2], [1, 2]);
+    }
+
+    #[test]
+    fn clear_rx_discards_data_and_errors() {
+        let mut uart = buffered_uart(8, 8);
+        uart.uart.rx_fifo.extend([Ok(1), Err(Error::ParityError)]);
+        uart.on_interrupt();
+        uart.uart.rx_fifo.push_back(Ok(2));
+        uart.clear_rx();
+        assert!(uart.uart.rx_fifo.is_empty());
+        let mut out = [0; 4];
+        assert_eq!(uart.read(&mut out), Ok(0));
+    }
+
+    #[test]
+    fn write_fills_tx_fifo_directly() {
+        let mut uart = buffered_uart(8, 32);
+        assert_eq!(uart.write(&[1, 2, 3]), 3);
+        assert_eq!(uart.uart.tx_fifo, [1, 2, 3]);
+        assert_eq!(uart.tx_free(), 32);
+        // Nothing is left in the ring buffer, so the TX interrupt stays disabled
+        assert!(!uart.uart.tx_irq);
+        assert_eq!(uart.flush(), Ok(()));
+    }
+
+    #[test]
+    fn write_buffers_bytes_until_tx_fifo_is_drained() {
+        let mut uart = buffered_uart(8, 32);
+        let data: Vec<u8> = (0..24).collect();
+        assert_eq!(uart.write(&data), 24);
+        assert_eq!(uart.uart.tx_fifo.len(), MOCK_FIFO_DEPTH);
+        assert_eq!(uart.tx_free(), 32 - 8);
+        assert!(uart.uart.tx_irq);
+        assert_eq!(uart.flush(), Err(nb::Error::WouldBlock));
+
+        // The hardware sends the FIFO content and the interrupt refills it
+        let mut sent: Vec<u8> = uart.uart.tx_fifo.drain(..).collect();
+        uart.on_interrupt();
+        assert!(!uart.uart.tx_irq);
+        sent.append(&mut uart.uart.tx_fifo);
+        assert_eq!(sent, data);
+        assert_eq!(uart.flush(), Ok(()));
+    }
+
+    #[test]
+    fn write_accepts_at_most_the_free_space() {
+        let mut uart = buffered_uart(8, 4);
+        let data: Vec<u8> = (0..24).collect();
+        assert_eq!(uart.write(&data), 4);
+        // The bytes were moved into the FIFO, so the ring buffer has space again
+        assert_eq!(uart.write(&data[4..]), 4);
+        assert_eq!(uart.write(&data[8..]), 4);
+        assert_eq!(uart.write(&data[12..]), 4);
+        assert_eq!(uart.uart.tx_fifo.len(), MOCK_FIFO_DEPTH);
+        assert_eq!(uart.write(&data[16..]), 4);
+        assert_eq!(uart.write(&data[20..]), 0);
+        assert!(uart.uart.tx_irq);
+    }
+
+    #[test]
+    fn flush_waits_for_transmitter() {
+        let mut uart = buffered_uart(8, 8);
+        uart.uart.tx_busy = true;
+        assert_eq!(uart.flush(), Err(nb::Error::WouldBlock));
+        uart.uart.tx_busy = false;
+        assert_eq!(uart.flush(), Ok(()));
+    }
+
+    #[test]
+    fn release_disables_irqs() {
+        let mut uart = buffered_uart(8, 8);
+        uart.write(&[1]);
+        let (mock, rx_buf, tx_buf) = uart.release();
+        assert!(!mock.rx_irqs);
+        assert!(!mock.tx_irq);
+        assert_eq!(rx_buf.len(), 8);
+        assert_eq!(tx_buf.len(), 8);
+    }
+}
@@ -0,0 +1,65 @@
+//! # embedded-hal-nb serial trait implementations
+//!
+//! Implementations of the [`embedded_hal_nb::serial`] traits for the UART types. These are
+//! only available if the `embedded-hal-nb` feature is enabled.
+use core::convert::Infallible;
+
+use embedded_hal::serial as serial_02;
+use embedded_hal_nb::serial::{self, ErrorKind};
+
+use super::{Error, Instance, Rx, Tx, Uart};
+
+impl serial::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Overrun => ErrorKind::Overrun,
+            Error::FramingError => ErrorKind::FrameFormat,
+            Error::ParityError => ErrorKind::Parity,
+            Error::BreakCondition => ErrorKind::Other,
+        }
+    }
+}
+
+impl<UART> serial::ErrorType for Tx<UART> {
+    type Error = Infallible;
+}
+
+impl<UART> serial::ErrorType for Rx<UART> {
+    type Error = Error;
+}
+
+impl<UART, PINS> serial::ErrorType for Uart<UART, PINS> {
+    type Error = Error;
+}
+
+impl<UART: Instance> serial::Write<u8> for Tx<UART> {
+    fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        serial_02::Write::write(self, word)
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        serial_02::Write::flush(self)
+    }
+}
+
+impl<UART: Instance> serial::Read<u8> for Rx<UART> {
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        serial_02::Read::read(self)
+    }
+}
+
+impl<UART: Instance, PINS> serial::Write<u8> for Uart<UART, PINS> {
+    fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        serial_02::Write::write(&mut self.tx, word).map_err(|e| e.map(|e| match e {}))
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        serial_02::Write::flush(&mut self.tx).map_err(|e| e.map(|e| match e {}))
+    }
+}
+
+impl<UART: Instance, PINS> serial::Read<u8> for Uart<UART, PINS> {
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        serial_02::Read::read(&mut self.rx)
+    }
+}
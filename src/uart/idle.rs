@@ -0,0 +1,152 @@
+//! # Idle line frame reception
+//!
+//! Many protocols delimit their packets by a quiet line instead of using a special framing
+//! format. The [`IdleLineRx`] uses the RX timeout feature of the UART to detect the end
+//! of a frame. The RX timeout condition is set when the RX FIFO contains data and there was no
+//! receiver activity for 4 character times.
+//!
+//! [`poll`](IdleLineRx::poll) can be called periodically or inside the UART interrupt handler.
+//! In the latter case, it is recommended to enable the [`Event::RxTimeout`](super::Event) and
+//! [`Event::RxFifoHalfFull`](super::Event) interrupts with [`Uart::listen`](super::Uart::listen)
+//! before splitting the UART.
+//!
+//! The last received word is only read from the RX FIFO once the line is idle, because the RX
+//! timeout condition requires data in the FIFO. The RX FIFO trigger level therefore needs to be
+//! at least 2 if the RX FIFO interrupt is used.
+use core::convert::Infallible;
+
+use embedded_hal::serial;
+
+use super::{Error, Instance, Rx};
+
+/// Complete frame received by the [`IdleLineRx`]
+#[derive(Debug, PartialEq)]
+pub struct Frame<'buf> {
+    /// Received frame data
+    pub data: &'buf [u8],
+    /// First error which occured during the reception of the frame. The erroneous words are
+    /// not contained in the frame data
+    pub error: Option<Error>,
+    /// The frame was completed because the maximum frame length was reached and not because the
+    /// line was idle
+    pub max_len_reached: bool,
+}
+
+/// Frame receiver which collects bytes until the line is idle or a maximum frame length is
+/// reached
+pub struct IdleLineRx<UART> {
+    rx: Rx<UART>,
+    buf: &'static mut [u8],
+    max_len: usize,
+    len: usize,
+    error: Option<Error>,
+    completed: bool,
+}
+
+impl<UART: Instance> Rx<UART> {
+    /// Convert the receiver into an idle line frame receiver. The maximum frame length is
+    /// the length of the passed buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer is empty
+    pub fn into_idle_line_rx(self, buf: &'static mut [u8]) -> IdleLineRx<UART> {
+        IdleLineRx::new(self, buf)
+    }
+}
+
+impl<UART: Instance> IdleLineRx<UART> {
+    /// Create a new frame receiver. The maximum frame length is the length of the passed buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer is empty
+    pub fn new(rx: Rx<UART>, buf: &'static mut [u8]) -> Self {
+        assert!(!buf.is_empty(), "the frame buffer must not be empty");
+        IdleLineRx {
+            rx,
+            max_len: buf.len(),
+            buf,
+            len: 0,
+            error: None,
+            completed: false,
+        }
+    }
+
+    /// Limit the maximum frame length. The length is clamped between 1 and the size of the
+    /// frame buffer
+    pub fn set_max_len(&mut self, max_len: usize) {
+        self.max_len = max_len.clamp(1, self.buf.len());
+    }
+
+    /// Number of bytes received for the current frame
+    #[inline]
+    pub fn pending(&self) -> usize {
+        self.len
+    }
+
+    /// Move received bytes from the RX FIFO into the frame buffer.
+    ///
+    /// Returns the complete frame when the RX timeout condition was detected or the maximum
+    /// frame length was reached. Otherwise, [nb::Error::WouldBlock] is returned. The frame
+    /// data is valid until the next call to this function
+    pub fn poll(&mut self) -> nb::Result<Frame<'_>, Infallible> {
+        if self.completed {
+            self.reset();
+        }
+        let uart = unsafe { &(*UART::ptr()) };
+        // The timeout condition is only set as long as there is data in the FIFO, so it
+        // needs to be checked before emptying the FIFO. The FIFO fill level is sampled right
+        // after the timeout flag, so the frame ends with the last word received before the
+        // line went idle. Words of the next frame which arrive in the meantime remain in the FIFO
+        let timeout = uart.rxstatus.read().rxto().bit_is_set();
+        let fifo_count = ((uart.state.read().bits() >> 8) & 0x1f) as usize;
+        // Without a timeout, the last word is left in the FIFO. Otherwise, the timeout
+        // condition could not be detected anymore once the line becomes idle
+        let words = if timeout {
+            fifo_count
+        } else {
+            fifo_count.saturating_sub(1)
+        };
+        for _ in 0..words {
+            if self.len >= self.max_len {
+                break;
+            }
+            match serial::Read::read(&mut self.rx) {
+                Ok(byte) => {
+                    self.buf[self.len] = byte;
+                    self.len += 1;
+                }
+                Err(nb::Error::WouldBlock) => break,
+                // Erroneous words are removed from the FIFO as well
+                Err(nb::Error::Other(err)) => {
+                    if self.error.is_none() {
+                        self.error = Some(err);
+                    }
+                }
+            }
+        }
+        let max_len_reached = self.len >= self.max_len;
+        if max_len_reached || (timeout && (self.len > 0 || self.error.is_some())) {
+            self.completed = true;
+            return Ok(Frame {
+                data: &self.buf[0..self.len],
+                error: self.error,
+                max_len_reached,
+            });
+        }
+        Err(nb::Error::WouldBlock)
+    }
+
+    /// Discard the current frame
+    pub fn reset(&mut self) {
+        self.len = 0;
+        self.error = None;
+        self.completed = false;
+    }
+
+    /// Release the receiver and the frame buffer
+    pub fn release(self) -> (Rx<UART>, &'static mut [u8]) {
+        (self.rx, self.buf)
+    }
+}
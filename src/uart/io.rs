@@ -0,0 +1,138 @@
+//! # embedded-io trait implementations
+//!
+//! Implementations of the [`embedded_io`] traits for the UART types. These are only available
+//! if the `embedded-io` feature is enabled.
+//!
+//! [`Read::read`] blocks until at least one byte was received and then returns all bytes which
+//! are available in the RX FIFO. [`Write::write`] blocks until at least one byte could be
+//! written into the TX FIFO. [`Write::flush`] blocks until the TX FIFO is empty and the
+//! transmitter is not busy anymore.
+use core::convert::Infallible;
+
+use embedded_hal::serial;
+use embedded_io::{ErrorKind, ErrorType, Read, ReadReady, Write, WriteReady};
+
+use super::{Error, Instance, Rx, Tx, Uart};
+
+impl embedded_io::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Overrun => ErrorKind::Other,
+            Error::FramingError | Error::ParityError | Error::BreakCondition => {
+                ErrorKind::InvalidData
+            }
+        }
+    }
+}
+
+impl<UART> ErrorType for Tx<UART> {
+    type Error = Infallible;
+}
+
+impl<UART> ErrorType for Rx<UART> {
+    type Error = Error;
+}
+
+impl<UART, PINS> ErrorType for Uart<UART, PINS> {
+    type Error = Error;
+}
+
+impl<UART: Instance> Write for Tx<UART> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        nb::block!(serial::Write::write(self, buf[0]))?;
+        let mut written = 1;
+        for byte in &buf[1..] {
+            if serial::Write::write(self, *byte).is_err() {
+                break;
+            }
+            written += 1;
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        nb::block!(serial::Write::flush(self))
+    }
+}
+
+impl<UART: Instance> WriteReady for Tx<UART> {
+    fn write_ready(&mut self) -> Result<bool, Self::Error> {
+        Ok(unsafe { &(*UART::ptr()) }
+            .txstatus
+            .read()
+            .wrrdy()
+            .bit_is_set())
+    }
+}
+
+impl<UART: Instance> Read for Rx<UART> {
+    /// Errors are only returned if no bytes were read yet. Otherwise, the bytes read so far
+    /// are returned and the error is returned by the next call
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        buf[0] = nb::block!(serial::Read::read(self))?;
+        let uart = unsafe { &(*UART::ptr()) };
+        let mut read = 1;
+        for byte in buf[1..].iter_mut() {
+            // Do not consume the next word if an error is pending, so the error is reported by
+            // the next call
+            let status = uart.rxstatus.read();
+            if status.rxovr().bit_is_set()
+                || status.rxbrk().bit_is_set()
+                || status.rxfrm().bit_is_set()
+                || status.rxpar().bit_is_set()
+            {
+                break;
+            }
+            match serial::Read::read(self) {
+                Ok(val) => *byte = val,
+                Err(_) => break,
+            }
+            read += 1;
+        }
+        Ok(read)
+    }
+}
+
+impl<UART: Instance> ReadReady for Rx<UART> {
+    fn read_ready(&mut self) -> Result<bool, Self::Error> {
+        Ok(unsafe { &(*UART::ptr()) }
+            .rxstatus
+            .read()
+            .rdavl()
+            .bit_is_set())
+    }
+}
+
+impl<UART: Instance, PINS> Write for Uart<UART, PINS> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        Write::write(&mut self.tx, buf).map_err(|e| match e {})
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Write::flush(&mut self.tx).map_err(|e| match e {})
+    }
+}
+
+impl<UART: Instance, PINS> WriteReady for Uart<UART, PINS> {
+    fn write_ready(&mut self) -> Result<bool, Self::Error> {
+        WriteReady::write_ready(&mut self.tx).map_err(|e| match e {})
+    }
+}
+
+impl<UART: Instance, PINS> Read for Uart<UART, PINS> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        Read::read(&mut self.rx, buf)
+    }
+}
+
+impl<UART: Instance, PINS> ReadReady for Uart<UART, PINS> {
+    fn read_ready(&mut self) -> Result<bool, Self::Error> {
+        ReadReady::read_ready(&mut self.rx)
+    }
+}
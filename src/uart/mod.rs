@@ -0,0 +1,1115 @@
+//! # API for the UART peripheral
+//!
+//! ## Examples
+//!
+//! - [UART example](https://github.com/robamu-org/va108xx-hal-rs/blob/main/examples/uart.rs)
+//! - [Buffered UART example](https://github.com/robamu-org/va108xx-hal-rs/blob/main/examples/uart-buffered.rs)
+//!
+//! ## Features
+//!
+//! - `embedded-hal-nb`: Implements the [`embedded_hal_nb::serial`] traits
+//! - `embedded-io`: Implements the [`embedded_io`] traits
+//! - `async`: Implements the [`embedded_io_async`] traits
+use core::{convert::Infallible, ptr};
+use core::{marker::PhantomData, ops::Deref};
+
+use crate::clock::{enable_peripheral_clock, PeripheralClocks};
+use crate::{
+    clock,
+    gpio::pins::{
+        AltFunc1, AltFunc2, AltFunc3, Pin, PA0, PA1, PA14, PA15, PA16, PA17, PA18, PA19, PA2, PA24,
+        PA25, PA26, PA27, PA28, PA29, PA3, PA30, PA31, PA6, PA7, PA8, PA9, PB16, PB17, PB18, PB19,
+        PB20, PB21, PB22, PB23, PB6, PB7, PB8, PB9,
+    },
+    pac::{uarta as uart_base, Interrupt, IRQSEL, SYSCONFIG, UARTA, UARTB},
+    prelude::*,
+    time::{Bps, Hertz},
+};
+
+use embedded_hal::{blocking, serial};
+
+mod autobaud;
+pub use autobaud::*;
+
+mod baud;
+pub use baud::*;
+
+mod buffered;
+pub use buffered::*;
+
+mod idle;
+pub use idle::*;
+
+mod multidrop;
+pub use multidrop::*;
+
+mod rs485;
+pub use rs485::*;
+
+mod self_test;
+pub use self_test::*;
+
+mod stats;
+pub use stats::*;
+
+mod status;
+pub use status::*;
+
+#[cfg(feature = "embedded-hal-nb")]
+mod hal_nb;
+
+#[cfg(feature = "embedded-io")]
+mod io;
+
+#[cfg(feature = "async")]
+mod asynch;
+#[cfg(feature = "async")]
+pub use asynch::*;
+
+const IRQ_DST_NONE: u32 = 0xffffffff;
+
+pub trait Pins<UART> {
+    /// The pins include the RTSn and CTSn pins required for hardware flow control
+    const FLOW_CONTROL: bool = false;
+}
+
+impl Pins<UARTA> for (Pin<PA9, AltFunc2>, Pin<PA8, AltFunc2>) {}
+impl Pins<UARTA> for (Pin<PA17, AltFunc3>, Pin<PA16, AltFunc3>) {}
+impl Pins<UARTA> for (Pin<PA31, AltFunc3>, Pin<PA30, AltFunc3>) {}
+
+impl Pins<UARTA> for (Pin<PB9, AltFunc1>, Pin<PB8, AltFunc1>) {}
+impl Pins<UARTA> for (Pin<PB23, AltFunc1>, Pin<PB22, AltFunc1>) {}
+
+impl Pins<UARTB> for (Pin<PA3, AltFunc2>, Pin<PA2, AltFunc2>) {}
+impl Pins<UARTB> for (Pin<PA19, AltFunc3>, Pin<PA18, AltFunc3>) {}
+impl Pins<UARTB> for (Pin<PA27, AltFunc3>, Pin<PA26, AltFunc3>) {}
+
+impl Pins<UARTB> for (Pin<PB7, AltFunc1>, Pin<PB6, AltFunc1>) {}
+impl Pins<UARTB> for (Pin<PB19, AltFunc2>, Pin<PB18, AltFunc2>) {}
+impl Pins<UARTB> for (Pin<PB21, AltFunc1>, Pin<PB20, AltFunc1>) {}
+
+// Pin tuples (TX, RX, RTSn, CTSn) for hardware flow control
+
+impl Pins<UARTA>
+    for (
+        Pin<PA9, AltFunc2>,
+        Pin<PA8, AltFunc2>,
+        Pin<PA7, AltFunc2>,
+        Pin<PA6, AltFunc2>,
+    )
+{
+    const FLOW_CONTROL: bool = true;
+}
+impl Pins<UARTA>
+    for (
+        Pin<PA17, AltFunc3>,
+        Pin<PA16, AltFunc3>,
+        Pin<PA15, AltFunc3>,
+        Pin<PA14, AltFunc3>,
+    )
+{
+    const FLOW_CONTROL: bool = true;
+}
+impl Pins<UARTA>
+    for (
+        Pin<PA31, AltFunc3>,
+        Pin<PA30, AltFunc3>,
+        Pin<PA29, AltFunc3>,
+        Pin<PA28, AltFunc3>,
+    )
+{
+    const FLOW_CONTROL: bool = true;
+}
+
+impl Pins<UARTB>
+    for (
+        Pin<PA3, AltFunc2>,
+        Pin<PA2, AltFunc2>,
+        Pin<PA1, AltFunc2>,
+        Pin<PA0, AltFunc2>,
+    )
+{
+    const FLOW_CONTROL: bool = true;
+}
+impl Pins<UARTB>
+    for (
+        Pin<PA27, AltFunc3>,
+        Pin<PA26, AltFunc3>,
+        Pin<PA25, AltFunc3>,
+        Pin<PA24, AltFunc3>,
+    )
+{
+    const FLOW_CONTROL: bool = true;
+}
+impl Pins<UARTB>
+    for (
+        Pin<PB19, AltFunc2>,
+        Pin<PB18, AltFunc2>,
+        Pin<PB17, AltFunc2>,
+        Pin<PB16, AltFunc2>,
+    )
+{
+    const FLOW_CONTROL: bool = true;
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Error {
+    Overrun,
+    FramingError,
+    ParityError,
+    /// A break was received. A break also causes a framing error, but it is reported as a
+    /// break condition instead
+    BreakCondition,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum ConfigError {
+    /// The baud rate is 0 or the clock divisor can not be represented by the clock scale
+    /// register for the given system clock
+    BaudrateUnreachable,
+    /// The error of the achievable baud rate exceeds the configured tolerance
+    BaudrateErrorTooLarge { error_ppm: u32 },
+    /// The transmitter is disabled while the TX FIFO still contains data, so it can not become
+    /// idle before the configuration is changed
+    TxDisabled,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum BreakError {
+    /// The break duration is 0 or exceeds [`MAX_BREAK_BITS`]
+    InvalidDuration,
+}
+
+/// Maximum break duration in bit times which can be generated with [`Tx::send_break`].
+/// Longer breaks can be generated with [`Tx::start_break`] and [`Tx::stop_break`]
+pub const MAX_BREAK_BITS: u8 = 0x7e;
+const TXBREAK_CONTINUOUS: u32 = 0x7f;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Event {
+    // Receiver FIFO interrupt enable. Generates interrupt
+    // when the FIFO count is >= RXFIFOIRQTRG, which is set with
+    // Config::rx_fifo_trigger_level and is half the FIFO depth by default
+    RxFifoHalfFull,
+    // Framing error, Overrun error, Parity Error and Break error
+    RxError,
+    // Event for timeout condition: Data in the FIFO and no receiver
+    // FIFO activity for 4 character times
+    RxTimeout,
+
+    // Transmitter FIFO interrupt enable. Generates interrupt
+    // when the FIFO count is < TXFIFOIRQTRG, which is set with
+    // Config::tx_fifo_trigger_level and is half the FIFO depth by default
+    TxFifoHalfFull,
+    // FIFO overflow error
+    TxError,
+    // Generate interrupt when transmit FIFO is empty and TXBUSY is 0
+    TxEmpty,
+    // Interrupt when CTSn changes value
+    TxCts,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Parity {
+    None,
+    Odd,
+    Even,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum StopBits {
+    One = 0,
+    Two = 1,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum WordSize {
+    Five = 0,
+    Six = 1,
+    Seven = 2,
+    Eight = 3,
+}
+
+#[derive(Copy, Clone)]
+pub struct Config {
+    pub baudrate: Bps,
+    pub parity: Parity,
+    pub stopbits: StopBits,
+    /// Oversampling rate. By default, the oversampling rate with the smaller baud rate error is
+    /// selected automatically
+    pub oversampling: Oversampling,
+    /// Maximum allowed error of the achieved baud rate in parts per million. The UART
+    /// constructor returns an error if this tolerance is exceeded
+    pub baud_tolerance_ppm: u32,
+    pub wordsize: WordSize,
+    pub enable_tx: bool,
+    pub enable_rx: bool,
+    /// Enable the automatic hardware flow control using the RTSn and CTSn signals. This is only
+    /// applied if the pins passed to the UART constructor include the RTSn and CTSn pins
+    pub hw_flow_control: bool,
+    /// RTSn is deasserted when the RX FIFO contains at least this many words in hardware flow
+    /// control mode. The RX FIFO has a depth of 16 words. Larger values are clamped to the
+    /// FIFO depth
+    pub rts_trigger_level: u8,
+    /// The RX FIFO interrupt is generated when the RX FIFO contains at least this many words.
+    /// Larger values than the FIFO depth are clamped
+    pub rx_fifo_trigger_level: u8,
+    /// The TX FIFO interrupt is generated when the TX FIFO contains less than this many words.
+    /// Larger values than the FIFO depth are clamped
+    pub tx_fifo_trigger_level: u8,
+    /// Connect the transmitter to the receiver internally
+    pub loopback: bool,
+    /// Enable the multidrop 9-bit address mode. The parity setting is ignored in this mode
+    pub multidrop: Option<MultidropConfig>,
+}
+
+impl Config {
+    pub fn baudrate(mut self, baudrate: Bps) -> Self {
+        self.baudrate = baudrate;
+        self
+    }
+
+    pub fn parity_none(mut self) -> Self {
+        self.parity = Parity::None;
+        self
+    }
+
+    pub fn parity_even(mut self) -> Self {
+        self.parity = Parity::Even;
+        self
+    }
+
+    pub fn parity_odd(mut self) -> Self {
+        self.parity = Parity::Odd;
+        self
+    }
+
+    pub fn stopbits(mut self, stopbits: StopBits) -> Self {
+        self.stopbits = stopbits;
+        self
+    }
+
+    pub fn wordsize(mut self, wordsize: WordSize) -> Self {
+        self.wordsize = wordsize;
+        self
+    }
+
+    /// Use the 8x baud clock instead of the standard 16x baud clock
+    pub fn baud8(mut self, baud: bool) -> Self {
+        self.oversampling = match baud {
+            false => Oversampling::X16,
+            true => Oversampling::X8,
+        };
+        self
+    }
+
+    pub fn oversampling(mut self, oversampling: Oversampling) -> Self {
+        self.oversampling = oversampling;
+        self
+    }
+
+    pub fn baud_tolerance_ppm(mut self, tolerance_ppm: u32) -> Self {
+        self.baud_tolerance_ppm = tolerance_ppm;
+        self
+    }
+
+    pub fn hw_flow_control(mut self, enable: bool) -> Self {
+        self.hw_flow_control = enable;
+        self
+    }
+
+    pub fn rts_trigger_level(mut self, level: u8) -> Self {
+        self.rts_trigger_level = level;
+        self
+    }
+
+    pub fn rx_fifo_trigger_level(mut self, level: u8) -> Self {
+        self.rx_fifo_trigger_level = level;
+        self
+    }
+
+    pub fn tx_fifo_trigger_level(mut self, level: u8) -> Self {
+        self.tx_fifo_trigger_level = level;
+        self
+    }
+
+    pub fn loopback(mut self, enable: bool) -> Self {
+        self.loopback = enable;
+        self
+    }
+
+    pub fn multidrop(mut self, multidrop: MultidropConfig) -> Self {
+        self.multidrop = Some(multidrop);
+        self
+    }
+}
+
+impl Config {
+    /// Clamp the FIFO trigger levels to the FIFO depth. This is done before the configuration
+    /// is applied, so the stored configuration matches the register values
+    fn clamp_trigger_levels(mut self) -> Self {
+        self.rts_trigger_level = clamp_trigger_level(self.rts_trigger_level);
+        self.rx_fifo_trigger_level = clamp_trigger_level(self.rx_fifo_trigger_level);
+        self.tx_fifo_trigger_level = clamp_trigger_level(self.tx_fifo_trigger_level);
+        self
+    }
+}
+
+#[inline]
+fn clamp_trigger_level(level: u8) -> u8 {
+    core::cmp::min(level, FIFO_DEPTH)
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        let baudrate = 115_200_u32.bps();
+        Config {
+            baudrate,
+            parity: Parity::None,
+            stopbits: StopBits::One,
+            oversampling: Oversampling::Auto,
+            baud_tolerance_ppm: 20_000,
+            wordsize: WordSize::Eight,
+            enable_tx: true,
+            enable_rx: true,
+            hw_flow_control: false,
+            rts_trigger_level: 8,
+            rx_fifo_trigger_level: FIFO_DEPTH / 2,
+            tx_fifo_trigger_level: FIFO_DEPTH / 2,
+            loopback: false,
+            multidrop: None,
+        }
+    }
+}
+
+impl From<Bps> for Config {
+    fn from(baud: Bps) -> Self {
+        Config::default().baudrate(baud)
+    }
+}
+
+/// Serial abstraction
+pub struct Uart<UART, PINS> {
+    uart: UART,
+    pins: PINS,
+    sys_clk: Hertz,
+    cfg: Config,
+    tx: Tx<UART>,
+    rx: Rx<UART>,
+}
+
+/// Resources of a split UART which are required to reunite the TX and RX halves with
+/// [`Uart::reunite`]
+pub struct UartParts<UART, PINS> {
+    uart: UART,
+    pins: PINS,
+    sys_clk: Hertz,
+    cfg: Config,
+}
+
+/// Serial receiver
+pub struct Rx<UART> {
+    _usart: PhantomData<UART>,
+}
+
+/// Serial transmitter
+pub struct Tx<UART> {
+    _usart: PhantomData<UART>,
+}
+
+impl<UART> Rx<UART> {
+    fn new() -> Self {
+        Self {
+            _usart: PhantomData,
+        }
+    }
+}
+
+impl<UART> Tx<UART> {
+    fn new() -> Self {
+        Self {
+            _usart: PhantomData,
+        }
+    }
+}
+
+pub trait Instance: Deref<Target = uart_base::RegisterBlock> {
+    /// Index of the UART peripheral, used for the IRQSEL interrupt redirection
+    const IDX: u8;
+
+    fn ptr() -> *const uart_base::RegisterBlock;
+}
+
+/// Returns [true] if the TX FIFO is empty and the transmitter is not busy anymore, which means
+/// that all written words were sent
+#[inline]
+pub(super) fn tx_idle<UART: Instance>() -> bool {
+    let uart = unsafe { &(*UART::ptr()) };
+    // The TX FIFO fill level is located in bits 24 to 28 of the STATE register
+    let tx_fifo_count = (uart.state.read().bits() >> 24) & 0x1f;
+    tx_fifo_count == 0 && uart.txstatus.read().txbusy().bit_is_clear()
+}
+
+impl<UART, PINS> Uart<UART, PINS>
+where
+    UART: Instance,
+{
+    /// This function assumes that the peripheral clock was alredy enabled
+    /// in the SYSCONFIG register
+    fn init(mut self) -> Result<Self, ConfigError> {
+        let config = self.cfg;
+        self.apply_config(&config)?;
+        let (txenb, rxenb) = (config.enable_tx, config.enable_rx);
+        // Clear the FIFO
+        self.uart.fifo_clr.write(|w| {
+            w.rxfifo().set_bit();
+            w.txfifo().set_bit()
+        });
+        self.uart.enable.write(|w| {
+            w.rxenable().bit(rxenb);
+            w.txenable().bit(txenb)
+        });
+        Ok(self)
+    }
+
+    /// Write the clock scale, control and trigger level registers. Nothing is written
+    /// if the baud rate can not be configured
+    fn apply_config(&mut self, config: &Config) -> Result<(), ConfigError> {
+        let config = config.clamp_trigger_levels();
+        let divisor = calculate_baud_divisor_checked(
+            self.sys_clk,
+            config.baudrate,
+            config.oversampling,
+            config.baud_tolerance_ppm,
+        )?;
+        self.uart
+            .clkscale
+            .write(|w| unsafe { w.bits(divisor.clk_scale()) });
+
+        // In multidrop mode, the sticky parity bit is used as the 9th bit. It is 0 for data
+        // words and set for address words with the DPARITY bit of the data register
+        let (paren, pareven, parstk) = match config.multidrop {
+            Some(_) => (true, true, true),
+            None => {
+                let (paren, pareven) = parity_bits(config.parity);
+                (paren, pareven, false)
+            }
+        };
+        let stopbits = match config.stopbits {
+            StopBits::One => false,
+            StopBits::Two => true,
+        };
+        let wordsize = config.wordsize as u8;
+        let baud8 = divisor.baud8;
+        let flow_control = config.hw_flow_control;
+        self.uart
+            .rxfifortstrg
+            .write(|w| unsafe { w.bits(config.rts_trigger_level as u32) });
+        self.uart
+            .rxfifoirqtrg
+            .write(|w| unsafe { w.bits(config.rx_fifo_trigger_level as u32) });
+        self.uart
+            .txfifoirqtrg
+            .write(|w| unsafe { w.bits(config.tx_fifo_trigger_level as u32) });
+        self.uart.ctrl.write(|w| {
+            w.paren().bit(paren);
+            w.pareven().bit(pareven);
+            w.parstk().bit(parstk);
+            w.stopbits().bit(stopbits);
+            w.baud8().bit(baud8);
+            w.autocts().bit(flow_control);
+            w.autorts().bit(flow_control);
+            w.loopback().bit(config.loopback);
+            unsafe { w.wordsize().bits(wordsize) }
+        });
+        match config.multidrop {
+            Some(multidrop) => {
+                self.uart
+                    .addr9mask
+                    .write(|w| unsafe { w.bits(multidrop.mask as u32) });
+                self.uart
+                    .addr9
+                    .write(|w| unsafe { w.bits(ADDR9_ENABLE | multidrop.address as u32) });
+            }
+            None => self.uart.addr9.reset(),
+        }
+        self.cfg = config;
+        Ok(())
+    }
+
+    /// Returns the current configuration
+    #[inline]
+    pub fn config(&self) -> &Config {
+        &self.cfg
+    }
+
+    /// Returns the clock divisor which is used for the current baud rate configuration
+    pub fn baud_divisor(&self) -> Result<BaudDivisor, ConfigError> {
+        calculate_baud_divisor(self.sys_clk, self.cfg.baudrate, self.cfg.oversampling)
+    }
+
+    /// Blocks until the TX FIFO is empty and the transmitter is not busy anymore. Returns
+    /// [`ConfigError::TxDisabled`] if the transmitter is disabled while the TX FIFO still
+    /// contains data, because the FIFO would never be emptied in that case
+    fn wait_tx_idle(&self) -> Result<(), ConfigError> {
+        while !tx_idle::<UART>() {
+            if self.uart.enable.read().txenable().bit_is_clear() {
+                // The TX FIFO fill level is located in bits 24 to 28 of the STATE register
+                let tx_fifo_count = (self.uart.state.read().bits() >> 24) & 0x1f;
+                if tx_fifo_count == 0 {
+                    break;
+                }
+                return Err(ConfigError::TxDisabled);
+            }
+        }
+        Ok(())
+    }
+
+    /// Wait for the transmitter to become idle, disable the UART, apply the changes
+    /// and restore the previous enable state. The FIFOs are not cleared
+    fn modify_while_idle<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> Result<T, ConfigError> {
+        self.wait_tx_idle()?;
+        let enable_state = self.uart.enable.read().bits();
+        self.uart.enable.reset();
+        let result = f(self);
+        self.uart.enable.write(|w| unsafe { w.bits(enable_state) });
+        Ok(result)
+    }
+
+    /// Change the baudrate at runtime. This function blocks until all pending data was sent.
+    /// The RX FIFO is preserved.
+    ///
+    /// The baud rate is not changed if it can not be configured within the configured tolerance
+    /// or if the transmitter is disabled while TX data is pending
+    pub fn set_baudrate(&mut self, baudrate: impl Into<Bps>) -> Result<BaudDivisor, ConfigError> {
+        let baudrate = baudrate.into();
+        let divisor = calculate_baud_divisor_checked(
+            self.sys_clk,
+            baudrate,
+            self.cfg.oversampling,
+            self.cfg.baud_tolerance_ppm,
+        )?;
+        self.modify_while_idle(|uart| {
+            uart.uart
+                .clkscale
+                .write(|w| unsafe { w.bits(divisor.clk_scale()) });
+            uart.uart.ctrl.modify(|_, w| w.baud8().bit(divisor.baud8));
+        })?;
+        self.cfg.baudrate = baudrate;
+        Ok(divisor)
+    }
+
+    /// Change the parity at runtime. This function blocks until all pending data was sent.
+    /// The RX FIFO is preserved. In multidrop mode, only the configuration is updated and the
+    /// parity is applied when the multidrop mode is disabled with [`reconfigure`](Self::reconfigure).
+    ///
+    /// Returns [`ConfigError::TxDisabled`] if the transmitter is disabled while TX data is pending
+    pub fn set_parity(&mut self, parity: Parity) -> Result<(), ConfigError> {
+        if self.cfg.multidrop.is_some() {
+            self.cfg.parity = parity;
+            return Ok(());
+        }
+        let (paren, pareven) = parity_bits(parity);
+        self.modify_while_idle(|uart| {
+            uart.uart.ctrl.modify(|_, w| {
+                w.paren().bit(paren);
+                w.pareven().bit(pareven)
+            });
+        })?;
+        self.cfg.parity = parity;
+        Ok(())
+    }
+
+    /// Change the number of stop bits at runtime. This function blocks until all pending data
+    /// was sent. The RX FIFO is preserved.
+    ///
+    /// Returns [`ConfigError::TxDisabled`] if the transmitter is disabled while TX data is pending
+    pub fn set_stopbits(&mut self, stopbits: StopBits) -> Result<(), ConfigError> {
+        self.modify_while_idle(|uart| {
+            uart.uart
+                .ctrl
+                .modify(|_, w| w.stopbits().bit(stopbits == StopBits::Two));
+        })?;
+        self.cfg.stopbits = stopbits;
+        Ok(())
+    }
+
+    /// Change the word size at runtime. This function blocks until all pending data was sent.
+    /// The RX FIFO is preserved.
+    ///
+    /// Returns [`ConfigError::TxDisabled`] if the transmitter is disabled while TX data is pending
+    pub fn set_wordsize(&mut self, wordsize: WordSize) -> Result<(), ConfigError> {
+        self.modify_while_idle(|uart| {
+            uart.uart
+                .ctrl
+                .modify(|_, w| unsafe { w.wordsize().bits(wordsize as u8) });
+        })?;
+        self.cfg.wordsize = wordsize;
+        Ok(())
+    }
+
+    pub fn listen(self, event: Event) -> Self {
+        self.uart.irq_enb.modify(|_, w| match event {
+            Event::RxError => w.irq_rx_status().set_bit(),
+            Event::RxFifoHalfFull => w.irq_rx().set_bit(),
+            Event::RxTimeout => w.irq_rx_to().set_bit(),
+            Event::TxEmpty => w.irq_tx_empty().set_bit(),
+            Event::TxError => w.irq_tx_status().set_bit(),
+            Event::TxFifoHalfFull => w.irq_tx().set_bit(),
+            Event::TxCts => w.irq_tx_cts().set_bit(),
+        });
+        self
+    }
+
+    pub fn unlisten(self, event: Event) -> Self {
+        self.uart.irq_enb.modify(|_, w| match event {
+            Event::RxError => w.irq_rx_status().clear_bit(),
+            Event::RxFifoHalfFull => w.irq_rx().clear_bit(),
+            Event::RxTimeout => w.irq_rx_to().clear_bit(),
+            Event::TxEmpty => w.irq_tx_empty().clear_bit(),
+            Event::TxError => w.irq_tx_status().clear_bit(),
+            Event::TxFifoHalfFull => w.irq_tx().clear_bit(),
+            Event::TxCts => w.irq_tx_cts().clear_bit(),
+        });
+        self
+    }
+
+    /// Route the UART interrupt to the given NVIC interrupt using the IRQSEL peripheral.
+    /// This also activates the peripheral clock for IRQSEL.
+    ///
+    /// The interrupt sources themselves still need to be enabled with [`listen`](Self::listen)
+    pub fn route_irq(&mut self, syscfg: &mut SYSCONFIG, irqsel: &mut IRQSEL, interrupt: Interrupt) {
+        enable_peripheral_clock(syscfg, PeripheralClocks::Irqsel);
+        irqsel.uart[UART::IDX as usize].write(|w| unsafe { w.bits(interrupt as u32) });
+    }
+
+    /// Disable the interrupt redirection for the UART in the IRQSEL peripheral
+    pub fn unroute_irq(&mut self, irqsel: &mut IRQSEL) {
+        irqsel.uart[UART::IDX as usize].write(|w| unsafe { w.bits(IRQ_DST_NONE) });
+    }
+
+    pub fn release(self) -> (UART, PINS) {
+        // Clear the FIFO
+        self.uart.fifo_clr.write(|w| {
+            w.rxfifo().set_bit();
+            w.txfifo().set_bit()
+        });
+        self.uart.enable.write(|w| {
+            w.rxenable().clear_bit();
+            w.txenable().clear_bit()
+        });
+        (self.uart, self.pins)
+    }
+
+    /// Returns [true] if the active-low CTSn input is asserted
+    #[inline]
+    pub fn cts(&self) -> bool {
+        self.tx.cts()
+    }
+
+    /// See [`Tx::send_break`]
+    #[inline]
+    pub fn send_break(&mut self, bit_times: u8) -> Result<(), BreakError> {
+        self.tx.send_break(bit_times)
+    }
+
+    /// See [`Rx::break_detected`]
+    #[inline]
+    pub fn break_detected(&self) -> bool {
+        self.rx.break_detected()
+    }
+
+    /// Set the value of the active-low RTSn output which is used when the automatic
+    /// hardware flow control is disabled
+    pub fn set_rts(&mut self, asserted: bool) {
+        self.uart.ctrl.modify(|_, w| w.defrts().bit(!asserted));
+    }
+
+    pub fn split(self) -> (Tx<UART>, Rx<UART>) {
+        (self.tx, self.rx)
+    }
+
+    /// Split the UART into the TX and RX halves. In contrast to [`split`](Self::split), the
+    /// peripheral and the pins are kept in a [`UartParts`] structure which allows to reunite
+    /// the halves with [`reunite`](Self::reunite)
+    pub fn split_with_parts(self) -> (Tx<UART>, Rx<UART>, UartParts<UART, PINS>) {
+        (
+            self.tx,
+            self.rx,
+            UartParts {
+                uart: self.uart,
+                pins: self.pins,
+                sys_clk: self.sys_clk,
+                cfg: self.cfg,
+            },
+        )
+    }
+
+    /// Reunite the TX and RX halves of a UART split with
+    /// [`split_with_parts`](Self::split_with_parts). The enable state of the halves is kept
+    pub fn reunite(tx: Tx<UART>, rx: Rx<UART>, parts: UartParts<UART, PINS>) -> Self {
+        Uart {
+            uart: parts.uart,
+            pins: parts.pins,
+            sys_clk: parts.sys_clk,
+            cfg: parts.cfg,
+            tx,
+            rx,
+        }
+    }
+}
+
+impl<UART: Instance, PINS: Pins<UART>> Uart<UART, PINS> {
+    /// Reconfigure the UART at runtime without releasing the peripheral. This function blocks
+    /// until all pending data was sent. The RX FIFO can be preserved optionally, but received
+    /// data might be corrupted if the configuration was changed while receiving data.
+    ///
+    /// If the baud rate can not be configured or the transmitter is disabled while TX data is
+    /// pending, an error is returned and the previous configuration remains active
+    pub fn reconfigure(
+        &mut self,
+        config: impl Into<Config>,
+        preserve_rx_fifo: bool,
+    ) -> Result<(), ConfigError> {
+        let mut config = config.into();
+        if !PINS::FLOW_CONTROL {
+            config.hw_flow_control = false;
+        }
+        // Check the baud rate before disabling the UART
+        calculate_baud_divisor_checked(
+            self.sys_clk,
+            config.baudrate,
+            config.oversampling,
+            config.baud_tolerance_ppm,
+        )?;
+        self.wait_tx_idle()?;
+        self.uart.enable.reset();
+        self.apply_config(&config)?;
+        self.uart.fifo_clr.write(|w| {
+            w.rxfifo().bit(!preserve_rx_fifo);
+            w.txfifo().set_bit()
+        });
+        self.uart.enable.write(|w| {
+            w.rxenable().bit(config.enable_rx);
+            w.txenable().bit(config.enable_tx)
+        });
+        Ok(())
+    }
+}
+
+fn parity_bits(parity: Parity) -> (bool, bool) {
+    match parity {
+        Parity::None => (false, false),
+        Parity::Odd => (true, false),
+        Parity::Even => (true, true),
+    }
+}
+
+macro_rules! uart_impl {
+    ($($UARTX:ident: ($uartx:ident, $idx:expr, $clk_enb_enum:path),)+) => {
+        $(
+            impl Instance for $UARTX {
+                const IDX: u8 = $idx;
+
+                fn ptr() -> *const uart_base::RegisterBlock {
+                    $UARTX::ptr() as *const _
+                }
+            }
+
+            impl<PINS: Pins<$UARTX>> Uart<$UARTX, PINS> {
+                /// Create a new UART peripheral driver.
+                ///
+                /// Returns a [`ConfigError`] if the configured baud rate can not be achieved
+                /// within the configured tolerance for the given system clock
+                pub fn $uartx(
+                    uart: $UARTX,
+                    pins: PINS,
+                    config: impl Into<Config>,
+                    syscfg: &mut SYSCONFIG,
+                    sys_clk: Hertz
+                ) -> Result<Self, ConfigError>
+                {
+                    let mut config = config.into();
+                    if !PINS::FLOW_CONTROL {
+                        config.hw_flow_control = false;
+                    }
+                    enable_peripheral_clock(syscfg, $clk_enb_enum);
+                    Uart { uart, pins, sys_clk, cfg: config, tx: Tx::new(), rx: Rx::new() }.init()
+                }
+            }
+        )+
+    }
+}
+
+uart_impl! {
+    UARTA: (uarta, 0, clock::PeripheralClocks::Uart0),
+    UARTB: (uartb, 1, clock::PeripheralClocks::Uart1),
+}
+
+impl<UART> Tx<UART>
+where
+    UART: Instance,
+{
+    /// Enable the transmitter
+    pub fn enable(&mut self) {
+        cortex_m::interrupt::free(|_| {
+            unsafe { &(*UART::ptr()) }
+                .enable
+                .modify(|_, w| w.txenable().set_bit());
+        });
+    }
+
+    /// Disable the transmitter. The receiver is not affected
+    pub fn disable(&mut self) {
+        cortex_m::interrupt::free(|_| {
+            unsafe { &(*UART::ptr()) }
+                .enable
+                .modify(|_, w| w.txenable().clear_bit());
+        });
+    }
+
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        unsafe { &(*UART::ptr()) }
+            .enable
+            .read()
+            .txenable()
+            .bit_is_set()
+    }
+
+    /// Clear the TX FIFO and the TX status
+    pub fn clear_fifo(&mut self) {
+        unsafe { &(*UART::ptr()) }.fifo_clr.write(|w| {
+            w.txfifo().set_bit();
+            w.txsts().set_bit()
+        });
+    }
+
+    /// Duration of one bit in system clock cycles, derived from the configured clock scale
+    pub fn bit_time_cycles(&self) -> u32 {
+        let uart = unsafe { &(*UART::ptr()) };
+        let baud_multiplier = match uart.ctrl.read().baud8().bit_is_set() {
+            false => 16,
+            true => 8,
+        };
+        // The clock scale register contains the divisor with 6 fractional bits
+        (uart.clkscale.read().bits() * baud_multiplier) / 64
+    }
+
+    /// Send a break with the given duration in bit times. The break is sent after all
+    /// data in the TX FIFO was transmitted.
+    ///
+    /// Returns [`BreakError::InvalidDuration`] if the duration is 0 or larger than
+    /// [`MAX_BREAK_BITS`]
+    pub fn send_break(&mut self, bit_times: u8) -> Result<(), BreakError> {
+        if bit_times == 0 || bit_times > MAX_BREAK_BITS {
+            return Err(BreakError::InvalidDuration);
+        }
+        unsafe { &(*UART::ptr()) }
+            .txbreak
+            .write(|w| unsafe { w.bits(bit_times as u32) });
+        Ok(())
+    }
+
+    /// Start a continuous break which lasts until [`stop_break`](Self::stop_break) is called
+    pub fn start_break(&mut self) {
+        unsafe { &(*UART::ptr()) }
+            .txbreak
+            .write(|w| unsafe { w.bits(TXBREAK_CONTINUOUS) });
+    }
+
+    /// Stop a continuous break started with [`start_break`](Self::start_break)
+    pub fn stop_break(&mut self) {
+        unsafe { &(*UART::ptr()) }
+            .txbreak
+            .write(|w| unsafe { w.bits(0) });
+    }
+
+    /// Returns [true] if the active-low CTSn input is asserted, allowing the transmitter to send
+    #[inline]
+    pub fn cts(&self) -> bool {
+        unsafe { &(*UART::ptr()) }
+            .txstatus
+            .read()
+            .txctsn()
+            .bit_is_clear()
+    }
+}
+
+impl<UART> Rx<UART>
+where
+    UART: Instance,
+{
+    /// Enable the receiver
+    pub fn enable(&mut self) {
+        cortex_m::interrupt::free(|_| {
+            unsafe { &(*UART::ptr()) }
+                .enable
+                .modify(|_, w| w.rxenable().set_bit());
+        });
+    }
+
+    /// Disable the receiver. The transmitter is not affected
+    pub fn disable(&mut self) {
+        cortex_m::interrupt::free(|_| {
+            unsafe { &(*UART::ptr()) }
+                .enable
+                .modify(|_, w| w.rxenable().clear_bit());
+        });
+    }
+
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        unsafe { &(*UART::ptr()) }
+            .enable
+            .read()
+            .rxenable()
+            .bit_is_set()
+    }
+
+    /// Clear the RX FIFO and the RX status
+    pub fn clear_fifo(&mut self) {
+        unsafe { &(*UART::ptr()) }.fifo_clr.write(|w| {
+            w.rxfifo().set_bit();
+            w.rxsts().set_bit()
+        });
+    }
+
+    /// Returns [true] if the next word in the RX FIFO is a break. The break can be consumed
+    /// with [`read`](serial::Read::read), which returns [`Error::BreakCondition`]
+    #[inline]
+    pub fn break_detected(&self) -> bool {
+        unsafe { &(*UART::ptr()) }
+            .rxstatus
+            .read()
+            .rxbrk()
+            .bit_is_set()
+    }
+
+    /// Returns [true] while a break is being received. This can be used to wait for the end of
+    /// a break, for example for LIN-style synchronization
+    #[inline]
+    pub fn receiving_break(&self) -> bool {
+        unsafe { &(*UART::ptr()) }
+            .rxstatus
+            .read()
+            .rxbusybrk()
+            .bit_is_set()
+    }
+
+    /// Returns [true] if the active-low RTSn output is asserted, signalling that the receiver
+    /// can accept more data
+    #[inline]
+    pub fn rts(&self) -> bool {
+        unsafe { &(*UART::ptr()) }
+            .rxstatus
+            .read()
+            .rxrtsn()
+            .bit_is_clear()
+    }
+}
+
+impl<UART, PINS> serial::Write<u8> for Uart<UART, PINS>
+where
+    UART: Instance,
+{
+    type Error = Infallible;
+    fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        self.tx.write(word)
+    }
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        self.tx.flush()
+    }
+}
+
+impl<UART: Instance, PINS> blocking::serial::write::Default<u8> for Uart<UART, PINS> {}
+
+impl<UART: Instance> serial::Write<u8> for Tx<UART> {
+    type Error = Infallible;
+
+    fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        let uart = unsafe { &(*UART::ptr()) };
+        let reader = uart.txstatus.read();
+        if reader.wrlost().bit_is_set() {
+            record_tx_overflow::<UART>();
+            uart.fifo_clr.write(|w| w.txsts().set_bit());
+        }
+        if reader.wrrdy().bit_is_clear() {
+            return Err(nb::Error::WouldBlock);
+        } else {
+            // The DPARITY bit is only used in multidrop mode, see Tx::write_address
+            unsafe {
+                // NOTE(unsafe) atomic write to data register
+                // NOTE(write_volatile) 8-bit write that's not
+                // possible through the svd2rust API
+                ptr::write_volatile(&(*UART::ptr()).data as *const _ as *mut _, word);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns [nb::Error::WouldBlock] until the TX FIFO is empty and the transmitter is not
+    /// busy anymore
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        if tx_idle::<UART>() {
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+}
+
+impl<UART: Instance, PINS> serial::Read<u8> for Uart<UART, PINS> {
+    type Error = Error;
+
+    fn read(&mut self) -> nb::Result<u8, Error> {
+        self.rx.read()
+    }
+}
+
+impl<UART: Instance> serial::Read<u8> for Rx<UART> {
+    type Error = Error;
+
+    fn read(&mut self) -> nb::Result<u8, Error> {
+        let uart = unsafe { &(*UART::ptr()) };
+        let status_reader = uart.rxstatus.read();
+        let err = if status_reader.rxovr().bit_is_set() {
+            Some(Error::Overrun)
+        } else if status_reader.rxbrk().bit_is_set() {
+            // A break also causes a framing error, so it needs to be checked first
+            Some(Error::BreakCondition)
+        } else if status_reader.rxfrm().bit_is_set() {
+            Some(Error::FramingError)
+        } else if status_reader.rxpar().bit_is_set() {
+            Some(Error::ParityError)
+        } else {
+            None
+        };
+        if let Some(err) = err {
+            record_errors::<UART>(err.into());
+            // The status code is always related to the next bit for the framing
+            // and parity status bits. We have to read the DATA register
+            // so that the next status reflects the next DATA word
+            // For overrun error, we read as well to clear the peripheral
+            uart.data.read().bits();
+            Err(err.into())
+        } else if status_reader.rdavl().bit_is_set() {
+            let data = uart.data.read().bits();
+            Ok((data & 0xff) as u8)
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+}
+
+impl<UART> core::fmt::Write for Tx<UART>
+where
+    Tx<UART>: embedded_hal::serial::Write<u8>,
+{
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        s.as_bytes()
+            .iter()
+            .try_for_each(|c| nb::block!(self.write(*c)))
+            .map_err(|_| core::fmt::Error)
+    }
+}
@@ -0,0 +1,88 @@
+//! # Multidrop 9-bit address mode
+//!
+//! In multidrop mode, several nodes share one UART bus. Every word carries a 9th bit which
+//! marks it as either an address or a data word. The UART uses the parity bit as the 9th bit,
+//! so the configured [`Parity`](super::Parity) is ignored in this mode.
+//!
+//! The mode is enabled with [`Config::multidrop`](super::Config::multidrop). The transmitter
+//! sends address words with [`Tx::write_address`]. The receiver compares received address
+//! words with the configured address and mask. Words are only placed into the RX FIFO after an
+//! address match, so the RX interrupts are only generated for frames addressed to this node.
+//! [`Rx::read_multidrop`] can be used to distinguish between address and data words.
+use core::convert::Infallible;
+
+use embedded_hal::serial;
+
+use super::{Error, Instance, Rx, Tx};
+
+/// Enables the address detection of the receiver. This bit is not modelled by the PAC
+pub(super) const ADDR9_ENABLE: u32 = 1 << 15;
+/// DPARITY bit of the data register, which is transmitted as the 9th bit of a word
+const DATA_DPARITY: u32 = 1 << 15;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct MultidropConfig {
+    /// Address of this node
+    pub address: u8,
+    /// Only the address bits set in the mask are compared. The default mask compares all bits
+    pub mask: u8,
+}
+
+impl MultidropConfig {
+    pub fn new(address: u8) -> Self {
+        MultidropConfig {
+            address,
+            mask: 0xff,
+        }
+    }
+
+    pub fn mask(mut self, mask: u8) -> Self {
+        self.mask = mask;
+        self
+    }
+}
+
+/// Word received in multidrop mode
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum MultidropWord {
+    /// Address word which matched the configured address
+    Address(u8),
+    Data(u8),
+}
+
+impl<UART: Instance> Tx<UART> {
+    /// Write an address word, which has the 9th bit set, to the TX FIFO. Regular writes send
+    /// data words. This requires the multidrop mode to be enabled
+    pub fn write_address(&mut self, address: u8) -> nb::Result<(), Infallible> {
+        let uart = unsafe { &(*UART::ptr()) };
+        if uart.txstatus.read().wrrdy().bit_is_clear() {
+            return Err(nb::Error::WouldBlock);
+        }
+        uart.data
+            .write(|w| unsafe { w.bits(DATA_DPARITY | address as u32) });
+        Ok(())
+    }
+}
+
+impl<UART: Instance> Rx<UART> {
+    /// Returns [true] if the next word in the RX FIFO is an address word which matched the
+    /// configured address
+    #[inline]
+    pub fn address_match(&self) -> bool {
+        unsafe { &(*UART::ptr()) }
+            .rxstatus
+            .read()
+            .rxaddr9()
+            .bit_is_set()
+    }
+
+    /// Read the next word in multidrop mode and report whether it is an address or a data word
+    pub fn read_multidrop(&mut self) -> nb::Result<MultidropWord, Error> {
+        let is_address = self.address_match();
+        let word = serial::Read::read(self)?;
+        match is_address {
+            true => Ok(MultidropWord::Address(word)),
+            false => Ok(MultidropWord::Data(word)),
+        }
+    }
+}
@@ -0,0 +1,418 @@
+//! # RS-485 half-duplex support
+//!
+//! RS-485 transceivers require a driver enable (DE) signal which needs to be asserted while
+//! transmitting and released as soon as the last stop bit was sent so the bus can be driven by
+//! other nodes. The [`Rs485Tx`] wraps a UART transmitter and controls a DE pin, which can be any
+//! [`OutputPin`], for example a typed [`Pin`](crate::gpio::Pin) or a
+//! [`DynPin`](crate::gpio::DynPin).
+//!
+//! The DE pin can be released in a blocking way with [`flush`](serial::Write::flush) or by
+//! calling [`on_interrupt`](Rs485Tx::on_interrupt) from the UART interrupt handler after enabling
+//! the interrupt mode with [`listen`](Rs485Tx::listen). The TX empty interrupt, which is generated
+//! when the TX FIFO is empty and the transmitter is not busy anymore, is used in interrupt mode.
+//! It is only enabled after the first word of a transmission was written to the TX FIFO, so it
+//! can not release the DE pin before the transmission started.
+//!
+//! The register accesses are performed through the [`Rs485Backend`] trait, which is implemented
+//! for the UART [`Tx`].
+use core::convert::Infallible;
+
+use embedded_hal::{blocking, digital::v2::OutputPin, serial};
+
+use super::{Instance, Tx};
+
+/// Register access required by the [`Rs485Tx`].
+///
+/// This trait is implemented for the UART [`Tx`]. It separates the driver enable handling from
+/// the hardware, so it can be tested on the host with a mock transmitter
+pub trait Rs485Backend: serial::Write<u8, Error = Infallible> {
+    /// Returns [true] if the TX FIFO is empty and the transmitter is not busy anymore
+    fn tx_empty(&self) -> bool;
+    /// Enable or disable the TX empty interrupt
+    fn set_tx_empty_irq(&mut self, enable: bool);
+    /// Duration of one bit in system clock cycles
+    fn bit_time_cycles(&self) -> u32;
+    /// Busy wait for at least the given number of system clock cycles
+    fn delay_cycles(&self, cycles: u32) {
+        cortex_m::asm::delay(cycles);
+    }
+}
+
+impl<UART: Instance> Rs485Backend for Tx<UART> {
+    #[inline]
+    fn tx_empty(&self) -> bool {
+        unsafe { &(*UART::ptr()) }
+            .irq_raw
+            .read()
+            .irq_tx_empty()
+            .bit_is_set()
+    }
+
+    #[inline]
+    fn set_tx_empty_irq(&mut self, enable: bool) {
+        unsafe { &(*UART::ptr()) }
+            .irq_enb
+            .modify(|_, w| w.irq_tx_empty().bit(enable));
+    }
+
+    #[inline]
+    fn bit_time_cycles(&self) -> u32 {
+        Tx::bit_time_cycles(self)
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub struct Rs485Config {
+    /// Turnaround delay in bit times between asserting the DE pin and sending the first byte
+    pub pre_delay_bits: u32,
+    /// Turnaround delay in bit times between the end of the last stop bit and releasing the
+    /// DE pin. In interrupt mode, this delay is performed inside the interrupt handler, so it
+    /// should be kept short
+    pub post_delay_bits: u32,
+    /// The DE pin is active low instead of active high
+    pub de_active_low: bool,
+}
+
+impl Rs485Config {
+    pub fn pre_delay_bits(mut self, bits: u32) -> Self {
+        self.pre_delay_bits = bits;
+        self
+    }
+
+    pub fn post_delay_bits(mut self, bits: u32) -> Self {
+        self.post_delay_bits = bits;
+        self
+    }
+
+    pub fn de_active_low(mut self, active_low: bool) -> Self {
+        self.de_active_low = active_low;
+        self
+    }
+}
+
+/// RS-485 transmitter which controls the driver enable pin of the transceiver. The transmitter
+/// is usually a UART [`Tx`]
+pub struct Rs485Tx<TX, DE> {
+    tx: TX,
+    de: DE,
+    cfg: Rs485Config,
+    de_asserted: bool,
+    irq_mode: bool,
+}
+
+impl<TX: Rs485Backend, DE: OutputPin> Rs485Tx<TX, DE> {
+    /// Create a new RS-485 transmitter. The DE pin is released initially
+    pub fn new(tx: TX, de: DE, cfg: Rs485Config) -> Result<Self, DE::Error> {
+        let mut rs485 = Rs485Tx {
+            tx,
+            de,
+            cfg,
+            de_asserted: true,
+            irq_mode: false,
+        };
+        rs485.release_de()?;
+        Ok(rs485)
+    }
+
+    /// Enable the interrupt mode. In this mode, the TX empty interrupt is enabled while the DE
+    /// pin is asserted and [`on_interrupt`](Self::on_interrupt) releases the DE pin
+    pub fn listen(&mut self) {
+        self.irq_mode = true;
+    }
+
+    /// Disable the interrupt mode
+    pub fn unlisten(&mut self) {
+        self.irq_mode = false;
+        self.tx.set_tx_empty_irq(false);
+    }
+
+    #[inline]
+    pub fn de_asserted(&self) -> bool {
+        self.de_asserted
+    }
+
+    /// Returns [true] if the TX FIFO is empty and the transmitter is not busy anymore
+    #[inline]
+    pub fn tx_empty(&self) -> bool {
+        self.tx.tx_empty()
+    }
+
+    /// This function should be called in the UART interrupt handler in interrupt mode.
+    /// It releases the DE pin if the transmission is complete and returns [true] in that case
+    pub fn on_interrupt(&mut self) -> Result<bool, DE::Error> {
+        if !self.de_asserted || !self.tx_empty() {
+            return Ok(false);
+        }
+        self.delay_bits(self.cfg.post_delay_bits);
+        self.release_de()?;
+        Ok(true)
+    }
+
+    /// Release the transmitter and the DE pin
+    pub fn release(mut self) -> Result<(TX, DE), DE::Error> {
+        self.unlisten();
+        self.release_de()?;
+        Ok((self.tx, self.de))
+    }
+
+    fn assert_de(&mut self) -> Result<(), DE::Error> {
+        if self.de_asserted {
+            return Ok(());
+        }
+        match self.cfg.de_active_low {
+            false => self.de.set_high()?,
+            true => self.de.set_low()?,
+        }
+        self.de_asserted = true;
+        self.delay_bits(self.cfg.pre_delay_bits);
+        Ok(())
+    }
+
+    fn release_de(&mut self) -> Result<(), DE::Error> {
+        if !self.de_asserted {
+            return Ok(());
+        }
+        if self.irq_mode {
+            self.tx.set_tx_empty_irq(false);
+        }
+        match self.cfg.de_active_low {
+            false => self.de.set_low()?,
+            true => self.de.set_high()?,
+        }
+        self.de_asserted = false;
+        Ok(())
+    }
+
+    /// The delay is performed one bit time at a time, so long delays at low baud rates can not
+    /// overflow the cycle count
+    fn delay_bits(&self, bits: u32) {
+        let bit_time = self.tx.bit_time_cycles();
+        for _ in 0..bits {
+            self.tx.delay_cycles(bit_time);
+        }
+    }
+}
+
+impl<TX: Rs485Backend, DE: OutputPin> serial::Write<u8> for Rs485Tx<TX, DE> {
+    type Error = DE::Error;
+
+    /// Asserts the DE pin if it is not asserted yet and writes a word to the TX FIFO
+    fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        self.assert_de()?;
+        if serial::Write::write(&mut self.tx, word).is_err() {
+            return Err(nb::Error::WouldBlock);
+        }
+        // The TX empty interrupt is only enabled once the FIFO contains data. Otherwise, it
+        // would be triggered immediately and the DE pin would be released too early
+        if self.irq_mode {
+            self.tx.set_tx_empty_irq(true);
+        }
+        Ok(())
+    }
+
+    /// Returns [nb::Error::WouldBlock] until the transmission is complete. The DE pin is
+    /// released after the configured turnaround delay afterwards
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        if !self.de_asserted {
+            return Ok(());
+        }
+        if !self.tx_empty() {
+            return Err(nb::Error::WouldBlock);
+        }
+        self.delay_bits(self.cfg.post_delay_bits);
+        self.release_de()?;
+        Ok(())
+    }
+}
+
+impl<TX: Rs485Backend, DE: OutputPin> blocking::serial::write::Default<u8> for Rs485Tx<TX, DE> {}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use core::cell::{Cell, RefCell};
+    use std::{rc::Rc, vec, vec::Vec};
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Copy, Clone)]
+    enum Step {
+        DeHigh,
+        DeLow,
+        Write(u8),
+        TxEmptyIrq(bool),
+    }
+
+    type Log = Rc<RefCell<Vec<Step>>>;
+
+    struct MockPin(Log);
+
+    impl OutputPin for MockPin {
+        type Error = Infallible;
+
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            self.0.borrow_mut().push(Step::DeHigh);
+            Ok(())
+        }
+
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            self.0.borrow_mut().push(Step::DeLow);
+            Ok(())
+        }
+    }
+
+    /// Mock transmitter. The TX empty interrupt fires as soon as it is enabled while the TX
+    /// FIFO is empty, which is recorded as a spurious interrupt
+    struct MockTx {
+        log: Log,
+        fifo: Vec<u8>,
+        spurious_irq: bool,
+        bit_time: u32,
+        delayed_cycles: Cell<u64>,
+    }
+
+    impl serial::Write<u8> for MockTx {
+        type Error = Infallible;
+
+        fn write(&mut self, word: u8) -> nb::Result<(), Infallible> {
+            self.log.borrow_mut().push(Step::Write(word));
+            self.fifo.push(word);
+            Ok(())
+        }
+
+        fn flush(&mut self) -> nb::Result<(), Infallible> {
+            Ok(())
+        }
+    }
+
+    impl Rs485Backend for MockTx {
+        fn tx_empty(&self) -> bool {
+            self.fifo.is_empty()
+        }
+
+        fn set_tx_empty_irq(&mut self, enable: bool) {
+            if enable && self.fifo.is_empty() {
+                self.spurious_irq = true;
+            }
+            self.log.borrow_mut().push(Step::TxEmptyIrq(enable));
+        }
+
+        fn bit_time_cycles(&self) -> u32 {
+            self.bit_time
+        }
+
+        fn delay_cycles(&self, cycles: u32) {
+            self.delayed_cycles
+                .set(self.delayed_cycles.get() + u64::from(cycles));
+        }
+    }
+
+    fn rs485(cfg: Rs485Config) -> (Rs485Tx<MockTx, MockPin>, Log) {
+        let log = Log::default();
+        let tx = MockTx {
+            log: log.clone(),
+            fifo: Vec::new(),
+            spurious_irq: false,
+            bit_time: 16,
+            delayed_cycles: Cell::new(0),
+        };
+        let rs485 = Rs485Tx::new(tx, MockPin(log.clone()), cfg).unwrap();
+        log.borrow_mut().clear();
+        (rs485, log)
+    }
+
+    #[test]
+    fn de_released_initially() {
+        let log = Log::default();
+        let tx = MockTx {
+            log: log.clone(),
+            fifo: Vec::new(),
+            spurious_irq: false,
+            bit_time: 16,
+            delayed_cycles: Cell::new(0),
+        };
+        let rs485 = Rs485Tx::new(tx, MockPin(log.clone()), Rs485Config::default()).unwrap();
+        assert!(!rs485.de_asserted());
+        assert_eq!(*log.borrow(), vec![Step::DeLow]);
+    }
+
+    #[test]
+    fn irq_mode_enables_tx_empty_irq_after_first_word() {
+        let (mut rs485, log) = rs485(Rs485Config::default());
+        rs485.listen();
+        serial::Write::write(&mut rs485, 0x55).unwrap();
+        serial::Write::write(&mut rs485, 0xaa).unwrap();
+        assert!(!rs485.tx.spurious_irq);
+        assert_eq!(
+            log.borrow()[..3],
+            [Step::DeHigh, Step::Write(0x55), Step::TxEmptyIrq(true)]
+        );
+        assert_eq!(log.borrow()[3], Step::Write(0xaa));
+    }
+
+    #[test]
+    fn on_interrupt_releases_de_once_tx_is_empty() {
+        let (mut rs485, log) = rs485(Rs485Config::default());
+        rs485.listen();
+        serial::Write::write(&mut rs485, 0x55).unwrap();
+        assert_eq!(rs485.on_interrupt(), Ok(false));
+        assert!(rs485.de_asserted());
+
+        rs485.tx.fifo.clear();
+        log.borrow_mut().clear();
+        assert_eq!(rs485.on_interrupt(), Ok(true));
+        assert!(!rs485.de_asserted());
+        assert_eq!(*log.borrow(), vec![Step::TxEmptyIrq(false), Step::DeLow]);
+        // Nothing happens if the DE pin was released already
+        assert_eq!(rs485.on_interrupt(), Ok(false));
+    }
+
+    #[test]
+    fn blocking_flush_releases_de() {
+        let (mut rs485, log) = rs485(Rs485Config::default());
+        serial::Write::write(&mut rs485, 0x55).unwrap();
+        assert_eq!(serial::Write::flush(&mut rs485), Err(nb::Error::WouldBlock));
+        assert!(rs485.de_asserted());
+        rs485.tx.fifo.clear();
+        assert_eq!(serial::Write::flush(&mut rs485), Ok(()));
+        assert!(!rs485.de_asserted());
+        // The TX empty interrupt is not used in blocking mode
+        assert_eq!(
+            *log.borrow(),
+            vec![Step::DeHigh, Step::Write(0x55), Step::DeLow]
+        );
+    }
+
+    #[test]
+    fn de_active_low() {
+        let (mut rs485, log) = rs485(Rs485Config::default().de_active_low(true));
+        serial::Write::write(&mut rs485, 0x55).unwrap();
+        rs485.tx.fifo.clear();
+        serial::Write::flush(&mut rs485).unwrap();
+        assert_eq!(
+            *log.borrow(),
+            vec![Step::DeLow, Step::Write(0x55), Step::DeHigh]
+        );
+    }
+
+    #[test]
+    fn turnaround_delays() {
+        let cfg = Rs485Config::default().pre_delay_bits(2).post_delay_bits(3);
+        let (mut rs485, _) = rs485(cfg);
+        serial::Write::write(&mut rs485, 0x55).unwrap();
+        assert_eq!(rs485.tx.delayed_cycles.get(), 2 * 16);
+        rs485.tx.fifo.clear();
+        serial::Write::flush(&mut rs485).unwrap();
+        assert_eq!(rs485.tx.delayed_cycles.get(), 5 * 16);
+    }
+
+    #[test]
+    fn long_delay_does_not_overflow() {
+        // 50 MHz / 300 baud, the product overflows a u32 for more than 25_769 bits
+        let (mut rs485, _) = rs485(Rs485Config::default().pre_delay_bits(30_000));
+        rs485.tx.bit_time = 166_667;
+        serial::Write::write(&mut rs485, 0x55).unwrap();
+        assert_eq!(rs485.tx.delayed_cycles.get(), 30_000 * 166_667);
+    }
+}
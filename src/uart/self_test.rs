@@ -0,0 +1,102 @@
+//! # Loopback self-test
+//!
+//! [`Uart::self_test`] enables the internal loopback of the UART, sends a test pattern through
+//! the FIFOs and compares the received words with the sent pattern. No external wiring between
+//! the TX and RX pins is required. The loopback block bit is set during the test, so the
+//! external lines do not interfere with the test.
+use embedded_hal::serial;
+
+use super::{read_word, ConfigError, ErrorFlags, Instance, Uart, WordSize};
+
+/// Default test pattern which toggles every data bit at least once
+pub const SELF_TEST_PATTERN: [u8; 8] = [0x55, 0xaa, 0x00, 0xff, 0x0f, 0xf0, 0x5a, 0xa5];
+
+/// Number of bit times to wait for a word to be received before it is considered lost
+const SELF_TEST_TIMEOUT_BITS: u32 = 24;
+
+/// Result of a UART self-test
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub struct SelfTestReport {
+    /// Number of sent words
+    pub sent: usize,
+    /// Number of received words
+    pub received: usize,
+    /// Number of received words which did not match the sent word
+    pub mismatches: usize,
+    /// Index, sent word and received word of the first mismatch
+    pub first_mismatch: Option<(usize, u8, u8)>,
+    /// All receiver errors which occured during the test
+    pub errors: ErrorFlags,
+}
+
+impl SelfTestReport {
+    /// Returns [true] if all words were received without mismatches and errors
+    pub fn passed(&self) -> bool {
+        self.received == self.sent && self.mismatches == 0 && self.errors.is_empty()
+    }
+}
+
+impl<UART: Instance, PINS> Uart<UART, PINS> {
+    /// Run a loopback self-test with the given pattern, for example [`SELF_TEST_PATTERN`].
+    ///
+    /// Pending TX data is sent before the test. Both FIFOs are cleared before and after the test,
+    /// so received data which was not read yet is lost. The previous loopback and enable settings
+    /// are restored afterwards. The error counters are not updated by the test.
+    ///
+    /// Returns [`ConfigError::TxDisabled`] if the transmitter is disabled while TX data is pending
+    pub fn self_test(&mut self, pattern: &[u8]) -> Result<SelfTestReport, ConfigError> {
+        let mask: u8 = match self.cfg.wordsize {
+            WordSize::Five => 0x1f,
+            WordSize::Six => 0x3f,
+            WordSize::Seven => 0x7f,
+            WordSize::Eight => 0xff,
+        };
+        let timeout_cycles = SELF_TEST_TIMEOUT_BITS * self.tx.bit_time_cycles();
+        self.modify_while_idle(|uart| {
+            let ctrl = uart.uart.ctrl.read().bits();
+            uart.uart.ctrl.modify(|_, w| {
+                w.loopback().set_bit();
+                w.loopbackblk().set_bit()
+            });
+            uart.tx.clear_fifo();
+            uart.rx.clear_fifo();
+            uart.uart.enable.write(|w| {
+                w.rxenable().set_bit();
+                w.txenable().set_bit()
+            });
+
+            let mut report = SelfTestReport::default();
+            for (idx, word) in pattern.iter().enumerate() {
+                nb::block!(serial::Write::write(&mut uart.tx, *word)).ok();
+                report.sent += 1;
+                let mut received = read_word::<UART>();
+                let mut waited = 0;
+                while received.is_none() && waited < timeout_cycles {
+                    cortex_m::asm::delay(64);
+                    waited += 64;
+                    received = read_word::<UART>();
+                }
+                let received = match received {
+                    Some(received) => received,
+                    None => continue,
+                };
+                report.received += 1;
+                report.errors.insert(received.errors);
+                if received.data & mask != word & mask {
+                    report.mismatches += 1;
+                    if report.first_mismatch.is_none() {
+                        report.first_mismatch = Some((idx, *word, received.data));
+                    }
+                }
+            }
+
+            // The transmitter is enabled during the test, so waiting can not fail
+            uart.wait_tx_idle().ok();
+            uart.uart.enable.reset();
+            uart.tx.clear_fifo();
+            uart.rx.clear_fifo();
+            uart.uart.ctrl.write(|w| unsafe { w.bits(ctrl) });
+            report
+        })
+    }
+}
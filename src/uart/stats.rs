@@ -0,0 +1,228 @@
+//! # UART error statistics
+//!
+//! Every UART has a set of [`ErrorCounters`] which are incremented whenever the receiver
+//! reports an error through [`serial::Read`](embedded_hal::serial::Read) or
+//! [`Rx::read_with_errors`] and whenever a word written to the TX FIFO was lost. Because the
+//! counters are also updated inside interrupt handlers, for example by the
+//! [`BufferedUart`](super::BufferedUart), they are stored inside a critical section mutex and
+//! can be read and reset atomically from the application.
+//!
+//! [`Rx::read_with_errors`] does not discard received words on errors and returns the word
+//! together with an [`ErrorFlags`] bitmap instead.
+use core::cell::Cell;
+use core::convert::Infallible;
+
+use cortex_m::interrupt::Mutex;
+
+use super::{Error, Instance, Rx, Tx, Uart};
+
+/// Accumulated error counts of a UART
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub struct ErrorCounters {
+    pub overrun: u32,
+    pub framing: u32,
+    pub parity: u32,
+    pub break_condition: u32,
+    /// Words which were lost because they were written to the full TX FIFO
+    pub tx_overflow: u32,
+}
+
+impl ErrorCounters {
+    const fn new() -> Self {
+        ErrorCounters {
+            overrun: 0,
+            framing: 0,
+            parity: 0,
+            break_condition: 0,
+            tx_overflow: 0,
+        }
+    }
+
+    fn record(&mut self, flags: ErrorFlags) {
+        if flags.contains(ErrorFlags::OVERRUN) {
+            self.overrun = self.overrun.saturating_add(1);
+        }
+        if flags.contains(ErrorFlags::FRAMING) {
+            self.framing = self.framing.saturating_add(1);
+        }
+        if flags.contains(ErrorFlags::PARITY) {
+            self.parity = self.parity.saturating_add(1);
+        }
+        if flags.contains(ErrorFlags::BREAK) {
+            self.break_condition = self.break_condition.saturating_add(1);
+        }
+    }
+}
+
+static ERROR_COUNTERS: [Mutex<Cell<ErrorCounters>>; 2] = [
+    Mutex::new(Cell::new(ErrorCounters::new())),
+    Mutex::new(Cell::new(ErrorCounters::new())),
+];
+
+fn modify_counters<UART: Instance>(f: impl FnOnce(&mut ErrorCounters)) {
+    cortex_m::interrupt::free(|cs| {
+        let cell = ERROR_COUNTERS[UART::IDX as usize].borrow(cs);
+        let mut counters = cell.get();
+        f(&mut counters);
+        cell.set(counters);
+    });
+}
+
+pub(super) fn record_errors<UART: Instance>(flags: ErrorFlags) {
+    if !flags.is_empty() {
+        modify_counters::<UART>(|counters| counters.record(flags));
+    }
+}
+
+pub(super) fn record_tx_overflow<UART: Instance>() {
+    modify_counters::<UART>(|counters| {
+        counters.tx_overflow = counters.tx_overflow.saturating_add(1)
+    });
+}
+
+fn error_counters<UART: Instance>() -> ErrorCounters {
+    cortex_m::interrupt::free(|cs| ERROR_COUNTERS[UART::IDX as usize].borrow(cs).get())
+}
+
+fn take_error_counters<UART: Instance>() -> ErrorCounters {
+    cortex_m::interrupt::free(|cs| ERROR_COUNTERS[UART::IDX as usize].borrow(cs).take())
+}
+
+/// Bitmap of the receiver errors which are related to a received word
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub struct ErrorFlags(u8);
+
+impl ErrorFlags {
+    pub const OVERRUN: ErrorFlags = ErrorFlags(1 << 0);
+    pub const FRAMING: ErrorFlags = ErrorFlags(1 << 1);
+    pub const PARITY: ErrorFlags = ErrorFlags(1 << 2);
+    pub const BREAK: ErrorFlags = ErrorFlags(1 << 3);
+
+    #[inline]
+    pub const fn empty() -> Self {
+        ErrorFlags(0)
+    }
+
+    #[inline]
+    pub const fn bits(&self) -> u8 {
+        self.0
+    }
+
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    #[inline]
+    pub const fn contains(&self, other: ErrorFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    #[inline]
+    pub fn insert(&mut self, other: ErrorFlags) {
+        self.0 |= other.0;
+    }
+
+    /// Most significant error, using the same priority as the [`serial::Read`] implementation
+    /// of the receiver
+    ///
+    /// [`serial::Read`]: embedded_hal::serial::Read
+    pub fn error(&self) -> Option<Error> {
+        if self.contains(Self::OVERRUN) {
+            Some(Error::Overrun)
+        } else if self.contains(Self::BREAK) {
+            Some(Error::BreakCondition)
+        } else if self.contains(Self::FRAMING) {
+            Some(Error::FramingError)
+        } else if self.contains(Self::PARITY) {
+            Some(Error::ParityError)
+        } else {
+            None
+        }
+    }
+}
+
+impl From<Error> for ErrorFlags {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Overrun => ErrorFlags::OVERRUN,
+            Error::FramingError => ErrorFlags::FRAMING,
+            Error::ParityError => ErrorFlags::PARITY,
+            Error::BreakCondition => ErrorFlags::BREAK,
+        }
+    }
+}
+
+/// Read the next word together with its error flags without updating the error counters
+pub(super) fn read_word<UART: Instance>() -> Option<RxWord> {
+    let uart = unsafe { &(*UART::ptr()) };
+    let status = uart.rxstatus.read();
+    let mut errors = ErrorFlags::empty();
+    if status.rxovr().bit_is_set() {
+        errors.insert(ErrorFlags::OVERRUN);
+    }
+    if status.rxbrk().bit_is_set() {
+        errors.insert(ErrorFlags::BREAK);
+    } else if status.rxfrm().bit_is_set() {
+        // A break also causes a framing error, which is only reported as a break
+        errors.insert(ErrorFlags::FRAMING);
+    }
+    if status.rxpar().bit_is_set() {
+        errors.insert(ErrorFlags::PARITY);
+    }
+    if status.rdavl().bit_is_clear() && errors.is_empty() {
+        return None;
+    }
+    // Reading the data register also clears the overrun condition
+    let data = (uart.data.read().bits() & 0xff) as u8;
+    Some(RxWord { data, errors })
+}
+
+/// Word received with [`Rx::read_with_errors`]
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct RxWord {
+    pub data: u8,
+    /// Errors which occured during the reception of this word. The data might be invalid if
+    /// this is not empty
+    pub errors: ErrorFlags,
+}
+
+impl<UART: Instance> Rx<UART> {
+    /// Read the next word together with all errors related to it. In contrast to the
+    /// [`serial::Read`](embedded_hal::serial::Read) implementation, the word is returned
+    /// even if an error occured. The error counters are updated
+    pub fn read_with_errors(&mut self) -> nb::Result<RxWord, Infallible> {
+        let word = read_word::<UART>().ok_or(nb::Error::WouldBlock)?;
+        record_errors::<UART>(word.errors);
+        Ok(word)
+    }
+
+    /// Returns the current error counters of this UART
+    pub fn error_counters(&self) -> ErrorCounters {
+        error_counters::<UART>()
+    }
+
+    /// Returns the current error counters of this UART and resets them atomically
+    pub fn take_error_counters(&mut self) -> ErrorCounters {
+        take_error_counters::<UART>()
+    }
+}
+
+impl<UART: Instance> Tx<UART> {
+    /// Returns the current error counters of this UART
+    pub fn error_counters(&self) -> ErrorCounters {
+        error_counters::<UART>()
+    }
+}
+
+impl<UART: Instance, PINS> Uart<UART, PINS> {
+    /// Returns the current error counters of this UART
+    pub fn error_counters(&self) -> ErrorCounters {
+        error_counters::<UART>()
+    }
+
+    /// Returns the current error counters of this UART and resets them atomically
+    pub fn take_error_counters(&mut self) -> ErrorCounters {
+        take_error_counters::<UART>()
+    }
+}
@@ -0,0 +1,182 @@
+//! # UART status snapshot
+//!
+//! [`Uart::status`] reads the RX status, TX status, raw interrupt and state registers and
+//! returns a decoded [`Status`] snapshot. This can be used to tune the FIFO trigger levels
+//! configured with [`Config::rx_fifo_trigger_level`](super::Config::rx_fifo_trigger_level) and
+//! [`Config::tx_fifo_trigger_level`](super::Config::tx_fifo_trigger_level).
+use super::{clamp_trigger_level, Instance, Uart};
+
+/// Depth of the RX and TX FIFO in words
+pub const FIFO_DEPTH: u8 = 16;
+
+/// Decoded RXSTATUS register
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub struct RxStatus {
+    /// Data is available in the RX FIFO
+    pub data_available: bool,
+    pub fifo_not_full: bool,
+    /// The receiver is currently receiving a word
+    pub busy: bool,
+    /// The RX FIFO contains data and there was no receiver activity for 4 character times
+    pub timeout: bool,
+    pub overrun: bool,
+    pub framing_error: bool,
+    pub parity_error: bool,
+    pub break_error: bool,
+    /// The receiver is currently receiving a break
+    pub busy_break: bool,
+    /// The next word in the RX FIFO is a matching address in multidrop mode
+    pub address_match: bool,
+    /// RTSn output is asserted
+    pub rts: bool,
+}
+
+/// Decoded TXSTATUS register
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub struct TxStatus {
+    /// The TX FIFO is not full and can accept another word
+    pub write_ready: bool,
+    pub fifo_full: bool,
+    /// The transmitter is currently sending a word
+    pub busy: bool,
+    /// A word was written to the full TX FIFO and was lost
+    pub write_lost: bool,
+    /// CTSn input is asserted
+    pub cts: bool,
+}
+
+/// Decoded IRQ_RAW register. The flags are set independently of the interrupt enable state
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub struct IrqStatus {
+    /// The RX FIFO count is at least the RX FIFO trigger level
+    pub rx: bool,
+    pub rx_status: bool,
+    pub rx_timeout: bool,
+    /// The TX FIFO count is below the TX FIFO trigger level
+    pub tx: bool,
+    pub tx_status: bool,
+    pub tx_empty: bool,
+    pub tx_cts: bool,
+}
+
+/// Snapshot of the UART status registers
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub struct Status {
+    pub rx: RxStatus,
+    pub tx: TxStatus,
+    pub irq_raw: IrqStatus,
+    /// Number of words in the RX FIFO
+    pub rx_fifo_count: u8,
+    /// Number of words in the TX FIFO
+    pub tx_fifo_count: u8,
+}
+
+impl Status {
+    /// Decode the raw values of the RXSTATUS, TXSTATUS, IRQ_RAW and STATE registers.
+    ///
+    /// This function does not access any hardware and can be used and tested on the host as well
+    pub fn from_raw(rx_status: u32, tx_status: u32, irq_raw: u32, state: u32) -> Self {
+        let bit = |val: u32, n: u32| (val >> n) & 1 == 1;
+        Status {
+            rx: RxStatus {
+                data_available: bit(rx_status, 0),
+                fifo_not_full: bit(rx_status, 1),
+                busy: bit(rx_status, 2),
+                timeout: bit(rx_status, 3),
+                overrun: bit(rx_status, 4),
+                framing_error: bit(rx_status, 5),
+                parity_error: bit(rx_status, 6),
+                break_error: bit(rx_status, 7),
+                busy_break: bit(rx_status, 8),
+                address_match: bit(rx_status, 9),
+                // RTSn is active low
+                rts: !bit(rx_status, 15),
+            },
+            tx: TxStatus {
+                write_ready: bit(tx_status, 0),
+                fifo_full: bit(tx_status, 1),
+                busy: bit(tx_status, 2),
+                write_lost: bit(tx_status, 3),
+                // CTSn is active low
+                cts: !bit(tx_status, 15),
+            },
+            irq_raw: IrqStatus {
+                rx: bit(irq_raw, 0),
+                rx_status: bit(irq_raw, 1),
+                rx_timeout: bit(irq_raw, 2),
+                tx: bit(irq_raw, 4),
+                tx_status: bit(irq_raw, 5),
+                tx_empty: bit(irq_raw, 6),
+                tx_cts: bit(irq_raw, 7),
+            },
+            // The FIFO fill levels are located in bits 8 to 12 and 24 to 28 of the STATE register
+            rx_fifo_count: ((state >> 8) & 0x1f) as u8,
+            tx_fifo_count: ((state >> 24) & 0x1f) as u8,
+        }
+    }
+}
+
+impl<UART: Instance, PINS> Uart<UART, PINS> {
+    /// Read a snapshot of the UART status registers
+    pub fn status(&self) -> Status {
+        Status::from_raw(
+            self.uart.rxstatus.read().bits(),
+            self.uart.txstatus.read().bits(),
+            self.uart.irq_raw.read().bits(),
+            self.uart.state.read().bits(),
+        )
+    }
+
+    /// Change the RX FIFO interrupt trigger level at runtime. The level is clamped to the FIFO
+    /// depth
+    pub fn set_rx_fifo_trigger_level(&mut self, level: u8) {
+        let level = clamp_trigger_level(level);
+        self.uart
+            .rxfifoirqtrg
+            .write(|w| unsafe { w.bits(level as u32) });
+        self.cfg.rx_fifo_trigger_level = level;
+    }
+
+    /// Change the TX FIFO interrupt trigger level at runtime. The level is clamped to the FIFO
+    /// depth
+    pub fn set_tx_fifo_trigger_level(&mut self, level: u8) {
+        let level = clamp_trigger_level(level);
+        self.uart
+            .txfifoirqtrg
+            .write(|w| unsafe { w.bits(level as u32) });
+        self.cfg.tx_fifo_trigger_level = level;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uart::Config;
+
+    #[test]
+    fn fifo_counts_are_decoded_from_state() {
+        let status = Status::from_raw(0, 0, 0, (3 << 24) | (16 << 8));
+        assert_eq!(status.rx_fifo_count, 16);
+        assert_eq!(status.tx_fifo_count, 3);
+    }
+
+    #[test]
+    fn irq_raw_fifo_flags() {
+        let status = Status::from_raw(0, 0, (1 << 4) | 1, 0);
+        assert!(status.irq_raw.rx);
+        assert!(status.irq_raw.tx);
+        assert!(!status.irq_raw.tx_empty);
+    }
+
+    #[test]
+    fn trigger_levels_are_clamped() {
+        let config = Config::default()
+            .rts_trigger_level(20)
+            .rx_fifo_trigger_level(17)
+            .tx_fifo_trigger_level(4)
+            .clamp_trigger_levels();
+        assert_eq!(config.rts_trigger_level, FIFO_DEPTH);
+        assert_eq!(config.rx_fifo_trigger_level, FIFO_DEPTH);
+        assert_eq!(config.tx_fifo_trigger_level, 4);
+    }
+}
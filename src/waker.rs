@@ -0,0 +1,40 @@
+//! Waker storage for the async drivers
+//!
+//! The async drivers register the waker of the waiting task before they enable the interrupts
+//! they are waiting for. The interrupt handlers mask these interrupts again and wake the task.
+use core::cell::RefCell;
+use core::task::Waker;
+
+use cortex_m::interrupt::Mutex;
+
+/// Waker of a task which waits for a peripheral interrupt
+pub(crate) struct IrqWaker {
+    waker: Mutex<RefCell<Option<Waker>>>,
+}
+
+impl IrqWaker {
+    pub(crate) const fn new() -> Self {
+        IrqWaker {
+            waker: Mutex::new(RefCell::new(None)),
+        }
+    }
+
+    /// Register the waker of the current task. A waker of another task is replaced
+    pub(crate) fn register(&self, waker: &Waker) {
+        cortex_m::interrupt::free(|cs| {
+            let mut slot = self.waker.borrow(cs).borrow_mut();
+            match slot.as_ref() {
+                Some(registered) if registered.will_wake(waker) => (),
+                _ => *slot = Some(waker.clone()),
+            }
+        });
+    }
+
+    /// Wake the registered task, if there is one
+    pub(crate) fn wake(&self) {
+        let waker = cortex_m::interrupt::free(|cs| self.waker.borrow(cs).borrow_mut().take());
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}